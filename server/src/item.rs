@@ -0,0 +1,134 @@
+use serde_derive::*;
+use std::fmt;
+use std::str::FromStr;
+
+// The type of the action run by the launcher, copy by default
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ActionKind {
+    #[default]
+    Copy,
+    Paste,
+    Url,
+    Open,
+    Reveal,
+    Terminal,
+    Rerun,
+    CopyFile,
+    Submit,
+}
+
+impl ActionKind {
+    pub const ALL: [ActionKind; 9] = [
+        ActionKind::Copy,
+        ActionKind::Paste,
+        ActionKind::Url,
+        ActionKind::Open,
+        ActionKind::Reveal,
+        ActionKind::Terminal,
+        ActionKind::Rerun,
+        ActionKind::CopyFile,
+        ActionKind::Submit,
+    ];
+
+    // The names of the "type" key
+    pub fn variants() -> [&'static str; 9] {
+        ActionKind::ALL.map(ActionKind::as_str)
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ActionKind::Copy => "copy",
+            ActionKind::Paste => "paste",
+            ActionKind::Url => "url",
+            ActionKind::Open => "open",
+            ActionKind::Reveal => "reveal",
+            ActionKind::Terminal => "terminal",
+            ActionKind::Rerun => "rerun",
+            ActionKind::CopyFile => "copyfile",
+            ActionKind::Submit => "submit",
+        }
+    }
+}
+
+impl FromStr for ActionKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        ActionKind::ALL
+            .iter()
+            .copied()
+            .find(|kind| kind.as_str().eq_ignore_ascii_case(s))
+            .ok_or_else(|| format!("valid values: {}", ActionKind::variants().join(", ")))
+    }
+}
+
+impl fmt::Display for ActionKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct Item {
+    pub title: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub subtitle: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub arg: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub icon: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub preview: Option<String>,
+    #[serde(default = "default_valid")]
+    pub valid: bool,
+    // The default action, the one given by the metadata is used if it's omitted
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub action: Option<ActionKind>,
+    #[serde(default)]
+    pub actions: Vec<Action>,
+    // The query put into the search field by tab(Alfred, Albert), or by the action of an invalid item
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub autocomplete: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct Action {
+    pub title: String,
+    pub arg: String,
+    #[serde(default, rename = "type")]
+    pub kind: ActionKind,
+}
+
+fn default_valid() -> bool {
+    true
+}
+
+impl Item {
+    pub fn text(title: &str) -> Item {
+        Item {
+            title: title.to_owned(),
+            subtitle: None,
+            arg: None,
+            icon: None,
+            preview: None,
+            valid: true,
+            action: None,
+            actions: vec![],
+            autocomplete: None,
+        }
+    }
+
+    pub fn arg(&self) -> &str {
+        self.arg.as_deref().unwrap_or(&self.title)
+    }
+
+    // The text shown in the preview pane, the whole arg by default
+    pub fn preview(&self) -> &str {
+        self.preview.as_deref().unwrap_or_else(|| self.arg())
+    }
+
+    pub fn action(&self) -> ActionKind {
+        self.action.unwrap_or_default()
+    }
+}
@@ -0,0 +1,148 @@
+//! Helper to run a binary in the server mode of cargo-launcher.
+//!
+//! The generated plugin starts the binary once with `CARGO_LAUNCHER_SERVER` set,
+//! and writes a request per line to stdin. The binary answers with a response per line to stdout.
+//!
+//! ```no_run
+//! use cargo_launcher_server::{is_server, serve, Item};
+//!
+//! fn search(args: &[String]) -> Result<Vec<Item>, String> {
+//!     Ok(vec![Item::text(&args.join(" "))])
+//! }
+//!
+//! fn main() {
+//!     if is_server() {
+//!         serve(search).unwrap();
+//!         return;
+//!     }
+//!     let args = std::env::args().skip(1).collect::<Vec<String>>();
+//!     for item in search(&args).unwrap() {
+//!         println!("{}", item.title);
+//!     }
+//! }
+//! ```
+use serde_derive::*;
+use std::env;
+use std::fmt::Display;
+use std::io::{self, BufRead, Write};
+
+mod item;
+
+pub use crate::item::{Action, ActionKind, Item};
+
+pub const SERVER_ENV: &str = "CARGO_LAUNCHER_SERVER";
+
+// The arguments are the ones passed in the normal mode, the subcommand args and the split query.
+// cargo-launcher writes it, the id is echoed in the response
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Request {
+    #[serde(default)]
+    pub id: u64,
+    #[serde(default)]
+    pub args: Vec<String>,
+}
+
+#[derive(Serialize)]
+struct Response {
+    id: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    items: Option<Vec<Item>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+pub fn is_server() -> bool {
+    env::var_os(SERVER_ENV).is_some()
+}
+
+// Answers the requests until stdin is closed, an error of the handler is shown as the error item
+pub fn serve<F, E>(handler: F) -> io::Result<()>
+where
+    F: FnMut(&[String]) -> Result<Vec<Item>, E>,
+    E: Display,
+{
+    let stdin = io::stdin();
+    let stdout = io::stdout();
+    serve_with(stdin.lock(), stdout.lock(), handler)
+}
+
+fn serve_with<R, W, F, E>(input: R, mut output: W, mut handler: F) -> io::Result<()>
+where
+    R: BufRead,
+    W: Write,
+    F: FnMut(&[String]) -> Result<Vec<Item>, E>,
+    E: Display,
+{
+    for line in input.lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let response = match serde_json::from_str::<Request>(&line) {
+            Ok(request) => match handler(&request.args) {
+                Ok(items) => Response {
+                    id: request.id,
+                    items: Some(items),
+                    error: None,
+                },
+                Err(err) => Response {
+                    id: request.id,
+                    items: None,
+                    error: Some(err.to_string()),
+                },
+            },
+            Err(err) => Response {
+                id: 0,
+                items: None,
+                error: Some(format!("Invalid request: {}", err)),
+            },
+        };
+        writeln!(output, "{}", serde_json::to_string(&response)?)?;
+        output.flush()?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+
+    use crate::*;
+    use std::io::Cursor;
+
+    fn run(input: &str) -> String {
+        let mut output = vec![];
+        serve_with(Cursor::new(input), &mut output, |args: &[String]| {
+            if args.is_empty() {
+                return Err("no args");
+            }
+            Ok(vec![Item::text(&args.join(" "))])
+        })
+        .unwrap();
+        String::from_utf8(output).unwrap()
+    }
+
+    #[test]
+    fn serve_items_ok() {
+        let actual =
+            run("{\"id\": 1, \"args\": [\"a\", \"b\"]}\n\n{\"id\": 2, \"args\": [\"c\"]}\n");
+        let expected = concat!(
+            r#"{"id":1,"items":[{"title":"a b","valid":true,"actions":[]}]}"#,
+            "\n",
+            r#"{"id":2,"items":[{"title":"c","valid":true,"actions":[]}]}"#,
+            "\n"
+        );
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn serve_error_ok() {
+        let actual = run("{\"id\": 3, \"args\": []}\n");
+        assert_eq!(actual, "{\"id\":3,\"error\":\"no args\"}\n");
+    }
+
+    #[test]
+    fn serve_invalid_request_ok() {
+        let actual = run("not json\n");
+        assert!(actual.starts_with("{\"id\":0,\"error\":\"Invalid request: "));
+    }
+}
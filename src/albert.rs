@@ -1,8 +1,10 @@
 use failure::*;
 use log::*;
+use serde_json::{json, Value};
 use std::fs;
 use std::path::PathBuf;
 
+use crate::cache::ERROR_ICON;
 use crate::cargo::CargoConfig;
 use crate::error::Result;
 use crate::fs::write_file;
@@ -36,15 +38,41 @@ impl<'a> Albert<'a> {
         let mut params = Param::new();
         params.insert("prettyname", conf.name());
         params.insert("version", conf.version());
-        params.insert("trigger", conf.name());
+        params.insert("trigger", &self.trigger());
         params.insert("author", &conf.author());
+        params.insert("name", conf.name());
+        params.insert("shim", &self.launcher_config.shim());
+        params.insert("bin", &self.launcher_config.bin());
+        params.insert("commands", &self.commands());
+        params.insert("debounce_ms", &conf.debounce_ms());
 
         let tpl = String::from_utf8_lossy(MODULE_TEMPLATE).into_owned();
-        let contents = tpl::render(&tpl, &params)?;
+        let contents = tpl::render_raw(&tpl, &params)?;
 
         Ok(contents)
     }
 
+    // Albert module has only one trigger, the query is matched in the module when there are more keywords
+    fn trigger(&self) -> String {
+        match &self.launcher_config.commands[..] {
+            [command] => command.keyword.clone(),
+            _ => String::new(),
+        }
+    }
+
+    fn commands(&self) -> Vec<Value> {
+        self.launcher_config
+            .commands
+            .iter()
+            .map(|command| {
+                json!({
+                    "keyword": command.keyword,
+                    "options": command.run_options(self.cargo_config),
+                })
+            })
+            .collect()
+    }
+
     fn module_path(&self) -> PathBuf {
         let mut buf = self.launcher_config.work_dir.clone();
         buf.push("__init__.py");
@@ -57,6 +85,13 @@ impl<'a> Albert<'a> {
         buf
     }
 
+    // Shown when the shim itself fails, the failures of the binary get the icon from the shim
+    fn error_icon_path(&self) -> PathBuf {
+        let mut buf = self.launcher_config.work_dir.clone();
+        buf.push("error.png");
+        buf
+    }
+
     fn icon(&self) -> Result<Vec<u8>> {
         self.launcher_config.icon()
     }
@@ -77,7 +112,10 @@ impl<'a> LauncherLike for Albert<'a> {
         let icon = self.icon_path();
         write_file(&icon, &self.icon()?[..])?;
 
-        Ok(vec![module, icon])
+        let error_icon = self.error_icon_path();
+        write_file(&error_icon, ERROR_ICON)?;
+
+        Ok(vec![module, icon, error_icon])
     }
 
     fn deploy(&self, paths: Vec<PathBuf>) -> Result<()> {
@@ -105,3 +143,62 @@ Installed path: "#;
         Ok(format!("{}{}", msg, path.to_string_lossy()))
     }
 }
+
+#[cfg(test)]
+mod tests {
+
+    use crate::albert::*;
+    use crate::subcommand::Subcommand;
+
+    #[test]
+    fn module_bin_subcommands_ok() {
+        let cargo_config = CargoConfig::dummy("test-bin");
+        let mut launcher_config = LauncherConfig::dummy();
+        launcher_config.commands.push(Subcommand {
+            keyword: String::from("test-bin-encode"),
+            title: String::from("test-bin encode"),
+            description: String::new(),
+            args: vec![String::from("encode")],
+        });
+        let albert = Albert::new(&cargo_config, &launcher_config);
+        let actual = albert.module_bin().unwrap();
+        assert!(!actual.contains("__trigger__"));
+        assert!(actual.contains(r#""keyword":"test-bin-encode""#));
+        assert!(actual.contains(r#""--prefix-arg","encode"]"#));
+    }
+
+    // The query after the keyword and the separating space, as findCommand slices it
+    fn find_command(script: &str, keyword: &str, query: &str) -> String {
+        let slice = script
+            .lines()
+            .map(str::trim)
+            .find(|line| line.starts_with("return command, query.string["))
+            .unwrap();
+        let start = slice["return command, query.string[".len()..]
+            .trim_end_matches(":]")
+            .split(" + ")
+            .map(|term| match term {
+                r#"len(command["keyword"])"# => keyword.len(),
+                n => n.parse().unwrap(),
+            })
+            .sum::<usize>();
+        query[start..].to_owned()
+    }
+
+    #[test]
+    fn find_command_ok() {
+        let cargo_config = CargoConfig::dummy("test-bin");
+        let mut launcher_config = LauncherConfig::dummy();
+        launcher_config.commands.push(Subcommand {
+            keyword: String::from("test-bin-encode"),
+            title: String::from("test-bin encode"),
+            description: String::new(),
+            args: vec![String::from("encode")],
+        });
+        let albert = Albert::new(&cargo_config, &launcher_config);
+        let script = albert.module_bin().unwrap();
+        assert!(script.contains(r#"query.string.startswith(command["keyword"] + " ")"#));
+        let actual = find_command(&script, "test-bin-encode", "test-bin-encode foo  bar");
+        assert_eq!(actual, "foo  bar");
+    }
+}
@@ -1,4 +1,5 @@
 use failure::bail;
+use serde_json::{json, Value};
 use std::fs::File;
 use std::io::Write;
 use std::path::PathBuf;
@@ -7,11 +8,16 @@ use zip::write::{FileOptions, ZipWriter};
 use crate::cargo::CargoConfig;
 use crate::core::*;
 use crate::error::Result;
+use crate::input::Input;
 use crate::launcher::{LauncherConfig, LauncherLike};
+use crate::notify::OutputMode;
+use crate::server::Mode;
 use crate::tpl::{self, Param};
 
 const INFO_PLIST: &[u8] = include_bytes!("asset/alfred/info.plist");
 const EXTENSION: &str = "alfredworkflow";
+const QUEUE_DELAY_IMMEDIATELY: u8 = 0;
+const QUEUE_DELAY_CUSTOM: u8 = 2;
 
 pub struct Alfred<'a> {
     cargo_config: &'a CargoConfig,
@@ -40,6 +46,10 @@ impl<'a> Alfred<'a> {
         params.insert("description", conf.description());
         params.insert("createdby", &conf.author());
         params.insert("buildid", &conf.build_id());
+        params.insert("shim_sh", &shell_words::quote(&self.launcher_config.shim()));
+        params.insert("bin_sh", &shell_words::quote(&self.launcher_config.bin()));
+        params.insert("filters", &self.script_filters());
+        params.insert("notify", &(conf.output() == OutputMode::Notify));
 
         let tpl = String::from_utf8_lossy(INFO_PLIST).into_owned();
         let info_plist = tpl::render(&tpl, &params)?;
@@ -47,11 +57,51 @@ impl<'a> Alfred<'a> {
         Ok(info_plist)
     }
 
+    // One script filter per keyword, they share the clipboard and notification outputs
+    fn script_filters(&self) -> Vec<Value> {
+        let (queue_delay_mode, queue_delay_custom) = queue_delay(self.cargo_config.debounce_ms());
+        self.launcher_config
+            .commands
+            .iter()
+            .enumerate()
+            .map(|(i, command)| {
+                json!({
+                    "uid": format!("D8A87EE6-C534-42F8-920D-{:012X}", i),
+                    "keyword": command.keyword,
+                    "title": command.title,
+                    "subtext": command.description,
+                    "options": shell_words::join(command.run_options(self.cargo_config)),
+                    "ypos": 170 + 130 * i,
+                    "queuedelaymode": queue_delay_mode,
+                    "queuedelaycustom": queue_delay_custom,
+                    "filtersresults": self.cargo_config.mode() == Mode::Filter,
+                    "history": self.cargo_config.history(),
+                    "select_uid": format!("E5F6A7B8-C9D0-4E1F-8A2B-{:012X}", i),
+                    // The selection comes by the universal action, the clipboard is read by the shim
+                    "selection": self.cargo_config.input() == Input::StdinFromSelection,
+                    "action_uid": format!("F1A2B3C4-D5E6-4F7A-8B9C-{:012X}", i),
+                    "vars_uid": format!("F1A2B3C4-D5E6-4F7A-8B9D-{:012X}", i),
+                    // The prompt item runs the submit script, the result is the notification
+                    "notify": self.cargo_config.output() == OutputMode::Notify,
+                    "submit_uid": format!("C7D8E9F0-A1B2-4C3D-8E4F-{:012X}", i + 1),
+                })
+            })
+            .collect()
+    }
+
     fn icon(&self) -> Result<Vec<u8>> {
         self.launcher_config.icon()
     }
 }
 
+// Alfred runs the script filter immediately, or after the custom delay in 0.1s(1 to 10)
+fn queue_delay(debounce_ms: u64) -> (u8, u64) {
+    match debounce_ms {
+        0 => (QUEUE_DELAY_IMMEDIATELY, 3),
+        ms => (QUEUE_DELAY_CUSTOM, ms.div_ceil(100).min(10)),
+    }
+}
+
 impl<'a> LauncherLike for Alfred<'a> {
     fn before_check(&self) -> Result<()> {
         if cfg!(not(target_os = "macos")) {
@@ -97,3 +147,38 @@ Powerpack is necessary to use Alfred workflow.
         Ok(msg.to_string())
     }
 }
+
+#[cfg(test)]
+mod tests {
+
+    use crate::alfred::*;
+    use crate::subcommand::Subcommand;
+
+    #[test]
+    fn info_plist_subcommands_ok() {
+        let cargo_config = CargoConfig::dummy("test-bin");
+        let mut launcher_config = LauncherConfig::dummy();
+        launcher_config.commands.push(Subcommand {
+            keyword: String::from("test-bin-encode"),
+            title: String::from("test-bin encode"),
+            description: String::from("Encode the input"),
+            args: vec![String::from("encode")],
+        });
+        let alfred = Alfred::new(&cargo_config, &launcher_config);
+        let actual = alfred.info_plist().unwrap();
+        assert_eq!(
+            actual.matches("alfred.workflow.input.scriptfilter").count(),
+            2
+        );
+        assert!(actual.contains("<string>test-bin-encode</string>"));
+        assert!(actual.contains("--keyword test-bin-encode --prefix-arg encode --"));
+        assert!(actual.contains("<key>D8A87EE6-C534-42F8-920D-000000000001</key>"));
+    }
+
+    #[test]
+    fn queue_delay_ok() {
+        assert_eq!(queue_delay(0), (0, 3));
+        assert_eq!(queue_delay(150), (2, 2));
+        assert_eq!(queue_delay(5000), (2, 10));
+    }
+}
@@ -1,5 +1,13 @@
+use crate::input::Input;
+use crate::item::ActionKind;
 use crate::launcher::Launcher;
+use crate::notify::OutputMode;
+use crate::preview::PreviewFormat;
+use crate::server::Mode;
+use crate::shim::Tokenize;
+use crate::usage::EmptyQuery;
 use std::path::PathBuf;
+use structopt::clap::AppSettings;
 use structopt::*;
 
 #[derive(StructOpt)]
@@ -15,8 +23,185 @@ pub struct Args {
     pub bin_name: Option<String>,
     #[structopt(short = "i", long = "icon", parse(from_os_str))]
     pub icon_path: Option<PathBuf>,
-    #[structopt(name = "launcher")]
+    #[structopt(long = "bin-path", parse(from_os_str))]
+    pub bin_path: Option<PathBuf>,
+    #[structopt(
+        name = "launcher",
+        raw(possible_values = "&Launcher::variants()", case_insensitive = "true")
+    )]
+    pub launcher: Option<Launcher>,
+    #[structopt(subcommand)]
+    pub sub: Option<Sub>,
+}
+
+// Parsed once per process, structopt 0.2 can't box the args
+#[allow(clippy::large_enum_variant)]
+#[derive(StructOpt, Debug)]
+pub enum Sub {
+    /// Run the binary and print the output in the launcher format(used by the generated plugins)
+    #[structopt(name = "run", raw(setting = "AppSettings::Hidden"))]
+    Run(RunArgs),
+    /// Manage the history of the generated plugins
+    #[structopt(name = "history")]
+    History(HistorySub),
+}
+
+#[derive(StructOpt, Debug)]
+pub enum HistorySub {
+    /// Remove the recent queries and the selected results of the binary
+    #[structopt(name = "clear")]
+    Clear {
+        #[structopt(name = "name")]
+        name: String,
+    },
+}
+
+#[derive(StructOpt, Debug)]
+pub struct RunArgs {
+    #[structopt(
+        long = "launcher",
+        raw(possible_values = "&Launcher::variants()", case_insensitive = "true")
+    )]
     pub launcher: Launcher,
+    #[structopt(
+        long = "tokenize",
+        default_value = "whitespace",
+        raw(possible_values = "&Tokenize::variants()", case_insensitive = "true")
+    )]
+    pub tokenize: Tokenize,
+    #[structopt(
+        long = "env",
+        parse(try_from_str = "parse_env"),
+        raw(number_of_values = "1")
+    )]
+    pub env: Vec<(String, String)>,
+    #[structopt(long = "cwd", parse(from_os_str))]
+    pub cwd: Option<PathBuf>,
+    #[structopt(long = "timeout-ms")]
+    pub timeout_ms: Option<u64>,
+    #[structopt(
+        long = "input",
+        default_value = "args",
+        raw(possible_values = "&Input::variants()", case_insensitive = "true")
+    )]
+    pub input: Input,
+    // Set by the plugins, they write the clipboard or the selection to the stdin of the shim
+    #[structopt(long = "stdin", raw(hidden = "true"))]
+    pub stdin: bool,
+    // Set by the plugins that run a shell command, the query is written to the stdin so the shell never parses it
+    #[structopt(long = "query-stdin", raw(hidden = "true"))]
+    pub query_stdin: bool,
+    #[structopt(
+        long = "action",
+        default_value = "copy",
+        raw(possible_values = "&ActionKind::variants()", case_insensitive = "true")
+    )]
+    pub action: ActionKind,
+    #[structopt(
+        long = "preview",
+        default_value = "text",
+        raw(
+            possible_values = "&PreviewFormat::variants()",
+            case_insensitive = "true"
+        )
+    )]
+    pub preview: PreviewFormat,
+    #[structopt(
+        long = "mode",
+        default_value = "run",
+        raw(possible_values = "&Mode::variants()", case_insensitive = "true")
+    )]
+    pub mode: Mode,
+    #[structopt(
+        long = "output",
+        default_value = "list",
+        raw(possible_values = "&OutputMode::variants()", case_insensitive = "true")
+    )]
+    pub output: OutputMode,
+    // Set by the plugins on the action of the notify output, the binary runs and the result is notified
+    #[structopt(long = "submit", raw(hidden = "true"))]
+    pub submit: bool,
+    // Set by the shim itself, the server host listens on the socket
+    #[structopt(long = "serve", parse(from_os_str), raw(hidden = "true"))]
+    pub serve: Option<PathBuf>,
+    #[structopt(long = "stream")]
+    pub stream: bool,
+    #[structopt(
+        long = "empty-query",
+        default_value = "run",
+        raw(possible_values = "&EmptyQuery::variants()", case_insensitive = "true")
+    )]
+    pub empty_query: EmptyQuery,
+    // Shown by the usage item of the empty query
+    #[structopt(long = "description")]
+    pub description: Option<String>,
+    #[structopt(long = "complete")]
+    pub complete: bool,
+    #[structopt(long = "history")]
+    pub history: bool,
+    // Set by the plugins on the action, the title of the selected item is recorded
+    #[structopt(long = "select", raw(hidden = "true"))]
+    pub select: Option<String>,
+    #[structopt(long = "cache-ttl-secs")]
+    pub cache_ttl_secs: Option<u64>,
+    #[structopt(long = "cache-max-entries", default_value = "100")]
+    pub cache_max_entries: usize,
+    // Set by the shim itself, the background runner of Alfred writes the output to the dir
+    #[structopt(long = "stream-dir", parse(from_os_str), raw(hidden = "true"))]
+    pub stream_dir: Option<PathBuf>,
+    #[structopt(long = "keyword")]
+    pub keyword: Option<String>,
+    #[structopt(
+        long = "prefix-arg",
+        raw(number_of_values = "1", allow_hyphen_values = "true")
+    )]
+    pub prefix_args: Vec<String>,
+    #[structopt(name = "bin")]
+    pub bin: String,
+    #[structopt(name = "query")]
+    pub query: Vec<String>,
+}
+
+#[cfg(test)]
+impl RunArgs {
+    pub fn dummy(bin: &str) -> RunArgs {
+        RunArgs {
+            launcher: Launcher::Shell,
+            tokenize: Tokenize::Whitespace,
+            env: vec![],
+            cwd: None,
+            timeout_ms: None,
+            input: Input::Args,
+            stdin: false,
+            query_stdin: false,
+            action: ActionKind::Copy,
+            preview: PreviewFormat::Text,
+            mode: Mode::Run,
+            output: OutputMode::List,
+            submit: false,
+            serve: None,
+            stream: false,
+            empty_query: EmptyQuery::Run,
+            description: None,
+            complete: false,
+            history: false,
+            select: None,
+            cache_ttl_secs: None,
+            cache_max_entries: 100,
+            stream_dir: None,
+            keyword: None,
+            prefix_args: vec![],
+            bin: bin.to_owned(),
+            query: vec![],
+        }
+    }
+}
+
+fn parse_env(s: &str) -> Result<(String, String), String> {
+    let pos = s
+        .find('=')
+        .ok_or_else(|| format!("invalid KEY=VALUE: no `=` found in `{}`", s))?;
+    Ok((s[..pos].to_owned(), s[pos + 1..].to_owned()))
 }
 
 pub fn args() -> Args {
@@ -1,51 +1,117 @@
 # -*- coding: utf-8 -*-
 
+import json
 import os
 import subprocess
+import time
 
 from albertv0 import *
 
 __iid__ = "PythonInterface/v0.2"
-__prettyname__ = "{{prettyname}}"
-__version__ = "{{version}}"
-__trigger__ = "{{trigger}}"
-__author__ = "{{author}}"
+__prettyname__ = {{ prettyname | json_encode }}
+__version__ = {{ version | json_encode }}
+{% if trigger %}__trigger__ = {{ trigger | json_encode }}
+{% endif %}__author__ = {{ author | json_encode }}
 __dependencies__ = []
 
 iconPath = os.path.join(os.path.dirname(__file__), 'icon.png')
-pathlist = ["/usr/local/bin", "~/.local/bin", "~/.cargo/bin"]
+errorIconPath = os.path.join(os.path.dirname(__file__), 'error.png')
+shim = {{ shim | json_encode }}
+bin = {{ bin | json_encode }}
+commands = {{ commands | json_encode }}
+debounceMs = {{ debounce_ms }}
+
+# Albert has no paste, reveal and copy file actions, they're run by the desktop commands
+def toAction(action, command):
+    kind = action.get("type", "copy")
+    text = action["text"]
+    arg = action["arg"]
+    if kind == "url":
+        return UrlAction(text, arg)
+    if kind == "open":
+        return ProcAction(text, ["xdg-open", arg])
+    if kind == "reveal":
+        return ProcAction(text, ["xdg-open", os.path.dirname(arg) or "."])
+    if kind == "terminal":
+        return TermAction(text, ["sh", "-c", arg])
+    if kind == "paste":
+        return ProcAction(text, ["sh", "-c", 'sleep 0.3 && xdotool type --clearmodifiers -- "$0"', arg])
+    if kind == "rerun":
+        return ProcAction(text, ["albert", "show", arg])
+    # The notify output runs the binary on the action only, the shim sends the notification
+    if kind == "submit":
+        return ProcAction(text, [shim, "launcher", "run", "--launcher", "albert", *command["options"], "--submit", "--", bin, arg])
+    if kind == "copyfile":
+        script = 'wl-copy < "$0" || xclip -selection clipboard -t "$(file -b --mime-type "$0")" -i "$0"'
+        return ProcAction(text, ["sh", "-c", script, arg])
+    return ClipAction(text, arg)
+
+def toItem(item, command):
+    # Tab puts the completion into the input line, albert keeps the input as is without it
+    extra = {"completion": item["completion"]} if "completion" in item else {}
+    return Item(
+        id = __prettyname__,
+        icon = item.get("icon", iconPath),
+        text = item["text"],
+        subtext = item["subtext"],
+        actions = [toAction(action, command) for action in item["actions"]],
+        **extra
+    )
+
+# The shim prints one JSON list, or one item per line when the results are streamed
+def parseLine(line):
+    line = line.decode("utf-8").strip()
+    if not line:
+        return []
+    parsed = json.loads(line)
+    return parsed if isinstance(parsed, list) else [parsed]
+
+def findCommand(query):
+    # A single keyword is registered as the trigger, multiple keywords are matched with the query
+    if query.isTriggered:
+        return commands[0], query.string
+    for command in commands:
+        if query.string.startswith(command["keyword"] + " "):
+            return command, query.string[len(command["keyword"]) + 1:]
+    return None, None
 
 def handleQuery(query):
-    if not query.isTriggered:
+    command, string = findCommand(query)
+    if command is None:
         return None
 
-    if len(query.string) <= 1:
-        return None
+    # The empty query is handled by the shim, it runs the binary or shows the usage or the recent queries
+    # Albert runs the queries in threads, the query typed further is no longer valid after the wait
+    if debounceMs > 0:
+        time.sleep(debounceMs / 1000)
+        if not getattr(query, "isValid", True):
+            return None
 
-    os.environ["PATH"] += os.pathsep + os.pathsep.join(pathlist)
-    cmd = ["url"] + query.string.split()
-    pipes = subprocess.run(cmd, stdout=subprocess.PIPE, stderr=subprocess.PIPE)
+    # The shim runs the binary and formats the output as albert items, the query is never evaluated by a shell
+    cmd = [shim, "launcher", "run", "--launcher", "albert", *command["options"], "--", bin, string]
+    proc = subprocess.Popen(cmd, stdout=subprocess.PIPE, stderr=subprocess.PIPE)
 
+    # The v0 interface has no incremental item API(Query.add is in the newer interfaces), the items are
+    # returned at once, the lines are read to stop the binary of the query typed further
     items = []
-    if pipes.returncode != 0:
-        err_msg = "%s. exit code: %s" % (pipes.stderr.strip().decode("utf-8"), pipes.returncode)
-        items.append(Item(
-            id = __prettyname__,
-            icon = iconPath,
-            text = err_msg,
-            subtext = "Failed",
-            actions = []
-        ))
-    else:
-        out = pipes.stdout.decode("utf-8")
+    for line in proc.stdout:
+        if not getattr(query, "isValid", True):
+            proc.kill()
+            return None
+        items.extend(toItem(item, command) for item in parseLine(line))
+    stderr = proc.stderr.read()
+    proc.wait()
+
+    # The shim reports the failures of the binary as items, this is the failure of the shim itself
+    if proc.returncode != 0:
+        details = stderr.decode("utf-8").strip()
+        line = details.splitlines()[0] if details else ""
         items.append(Item(
             id = __prettyname__,
-            icon = iconPath,
-            text = out,
-            subtext = "Success",
-            actions = [
-                ClipAction("Added to Clipboard", out)
-            ]
+            icon = errorIconPath,
+            text = "cargo-launcher failed",
+            subtext = "Exit code %s: %s" % (proc.returncode, line),
+            actions = [ClipAction("Copy stderr", details)] if details else []
         ))
-    return items
 
+    return items
@@ -1,50 +1,207 @@
 'use strict';
 
-const exec = require('child_process').exec;
+const { spawn } = require('child_process');
+
+const SHIM = {{ shim | json_encode }};
+const BIN = {{ bin | json_encode }};
+const OPTIONS = {{ options | json_encode }};
+const DEBOUNCE_MS = {{ debounce_ms }};
+const INPUT = {{ input | json_encode }};
+// The selected items are recorded by the shim to rank them higher next time
+const HISTORY = OPTIONS.includes('--history');
 
 module.exports = (pluginContext) => {
   const app = pluginContext.app;
   const toast = pluginContext.toast;
   const clipboard = pluginContext.clipboard;
+  const shell = pluginContext.shell;
   const logger = pluginContext.logger;
 
-  function startup() {
-    process.env.PATH = "~/.cargo/bin:~/.local/bin:/usr/local/bin:" + process.env.PATH;
-  }
+  let current = null;
+  let timer = null;
 
   function search(query, res) {
     logger.log('query: ' + query);
-    logger.log('res: ' + res);
-    exec(`{{name}} ${query}`, (err, stdout, stderr) => {
-      if (err) {
-        // todo show error
-        logger.log(`error: ${err}`);
+    // The previous query is no longer shown, its results are dropped
+    clearTimeout(timer);
+    if (current) {
+      current.kill();
+      current = null;
+    }
+    if (DEBOUNCE_MS > 0) {
+      timer = setTimeout(() => run(query, res), DEBOUNCE_MS);
+    } else {
+      run(query, res);
+    }
+  }
+
+  function run(query, res) {
+    // The shim runs the binary and formats the output as hain items, the query is never evaluated by a shell
+    // The clipboard is written to the stdin of the shim, the selection is read by the shim itself
+    const stdin = INPUT === 'stdin-from-clipboard' ? ['--stdin'] : [];
+    const args = ['launcher', 'run', '--launcher', 'hain', ...OPTIONS, ...stdin, '--', BIN, query];
+    const child = spawn(SHIM, args);
+    current = child;
+    // The shim killed by the next query closes the pipe
+    child.stdin.on('error', (err) => logger.log(`stdin: ${err}`));
+    if (stdin.length > 0) {
+      Promise.resolve(clipboard.readText()).then((text) => child.stdin.end(text || ''));
+    } else {
+      child.stdin.end();
+    }
+    let buffer = '';
+    let stderr = '';
+    let failed = false;
+    child.stdout.on('data', (data) => {
+      buffer += data;
+      const lines = buffer.split('\n');
+      buffer = lines.pop();
+      lines.forEach((line) => addLine(res, line));
+    });
+    child.stderr.on('data', (data) => {
+      stderr += data;
+    });
+    child.on('error', (err) => {
+      // The shim reports the failures of the binary as items, this is the failure of the shim itself
+      logger.log(`error: ${err}`);
+      failed = true;
+      res.add(failedItem(err, stderr));
+    });
+    child.on('close', (code, signal) => {
+      if (signal || failed) {
+        return;
       }
-      logger.log(`stdout: ${stdout}`);
+      addLine(res, buffer);
+      if (code !== 0) {
+        logger.log(`error: exit code ${code}`);
+        res.add(failedItem({ code, message: `Exit code ${code}` }, stderr));
+      }
+    });
+  }
+
+  // The shim prints one JSON array, or one item per line when the results are streamed
+  function addLine(res, line) {
+    if (!line.trim()) {
+      return;
+    }
+    const parsed = JSON.parse(line);
+    (Array.isArray(parsed) ? parsed : [parsed]).forEach((item) => res.add(item));
+  }
 
-      res.add({
-        id: 'ok',
-        payload: stdout,
-        title: `<b>${stdout}</b>`
-      });
+  function escape(text) {
+    return text.replace(/&/g, '&amp;').replace(/</g, '&lt;').replace(/>/g, '&gt;');
+  }
+
+  function failedItem(err, stderr) {
+    const details = (stderr || err.message).trim();
+    const line = details.split('\n')[0];
+    return {
+      id: 'copy',
+      payload: { arg: details, html: `<pre>${escape(details)}</pre>` },
+      title: '<b>cargo-launcher failed</b>',
+      desc: escape(typeof err.code === 'number' ? `Exit code ${err.code}: ${line}` : line),
+      icon: '#fa fa-times-circle',
+      preview: true,
+    };
+  }
+
+  function copy(payload) {
+    clipboard.writeText(payload).then((result) => {
+      toast.enqueue(`Added to clipboard: ${payload}`);
+      // TODO workaround...
+      setTimeout(() => {
+        app.close();
+      }, 1000);
+    });
+  }
+
+  // The terminal is opened by the platform command, hain has no API for it
+  function terminal(payload) {
+    if (process.platform === 'win32') {
+      spawn('cmd.exe', ['/c', 'start', 'cmd.exe', '/k', payload], { detached: true });
+    } else if (process.platform === 'darwin') {
+      const script = `tell application "Terminal" to do script ${JSON.stringify(payload)}`;
+      spawn('osascript', ['-e', script, '-e', 'tell application "Terminal" to activate'], { detached: true });
+    } else {
+      spawn('x-terminal-emulator', ['-e', 'sh', '-c', `${payload}; exec "$SHELL"`], { detached: true });
+    }
+    app.close();
+  }
+
+  // The notify output runs the binary on the execute only, the result is shown as a toast
+  function submit(query) {
+    const stdin = INPUT === 'stdin-from-clipboard' ? ['--stdin'] : [];
+    const args = ['launcher', 'run', '--launcher', 'hain', ...OPTIONS, ...stdin, '--submit', '--', BIN, query];
+    const child = spawn(SHIM, args);
+    let stdout = '';
+    child.stdout.on('data', (data) => {
+      stdout += data;
+    });
+    child.stdin.on('error', (err) => logger.log(`stdin: ${err}`));
+    if (stdin.length > 0) {
+      Promise.resolve(clipboard.readText()).then((text) => child.stdin.end(text || ''));
+    } else {
+      child.stdin.end();
+    }
+    child.on('error', (err) => toast.enqueue(`\u2717 cargo-launcher failed: ${err.message}`));
+    child.on('close', (code) => {
+      if (code !== 0) {
+        toast.enqueue(`\u2717 cargo-launcher failed: exit code ${code}`);
+        return;
+      }
+      // Hain has one style of the toast, the mark tells the failure
+      const result = JSON.parse(stdout);
+      const mark = result.success ? '\u2713' : '\u2717';
+      toast.enqueue(`${mark} ${result.title}: ${result.message}`);
     });
   }
 
+  function select(title) {
+    const args = ['launcher', 'run', '--launcher', 'hain', ...OPTIONS, '--select', title, '--', BIN];
+    spawn(SHIM, args, { detached: true, stdio: 'ignore' }).unref();
+  }
+
+  // The id is the action of the item, hain can't paste so it's copied instead
+  // The payload has the arg, the title and the preview html of the item
   function execute(id, payload) {
-    if (id === 'ok') {
-      clipboard.writeText(payload).then((result) => {
-        toast.enqueue(`Added to clipboard: ${payload}`);
-        // TODO workaround...
-        setTimeout(() => {
-          app.close();
-        }, 1000);
-      });
+    const arg = payload.arg;
+    if (HISTORY && id !== 'invalid' && typeof payload.title === 'string') {
+      select(payload.title);
+    }
+    switch (id) {
+      case 'copy':
+      case 'paste':
+      // Hain copies the text only, the path of the file is copied
+      case 'copyfile':
+        copy(arg);
+        break;
+      case 'url':
+        shell.openExternal(arg);
+        app.close();
+        break;
+      case 'open':
+        shell.openItem(arg);
+        app.close();
+        break;
+      case 'reveal':
+        shell.showItemInFolder(arg);
+        app.close();
+        break;
+      case 'terminal':
+        terminal(arg);
+        break;
+      case 'rerun':
+        app.setQuery(arg);
+        break;
+      case 'submit':
+        submit(arg);
+        break;
     }
   }
 
   function renderPreview(id, payload, render) {
-    render('<html><body>Something</body></html>');
+    render(payload.html);
   }
 
-  return { startup, search, execute, renderPreview };
+  return { search, execute, renderPreview };
 };
@@ -0,0 +1,250 @@
+--- === {{spoon}} ===
+---
+--- {{description}}
+---
+--- Generated by cargo-launcher
+
+local obj = {}
+obj.__index = obj
+
+-- Metadata
+obj.name = {{ spoon | json_encode }}
+obj.version = {{ version | json_encode }}
+obj.author = {{ author | json_encode }}
+obj.license = "MIT - https://opensource.org/licenses/MIT"
+
+obj.bin = {{ bin | json_encode }}
+obj.shim = {{ shim | json_encode }}
+-- The shim options are embedded as a JSON string, Lua has no array literal compatible with JSON
+obj.options = hs.json.decode({{ options | json_encode | json_encode }})
+obj.debounce = {{ debounce_ms }} / 1000
+obj.input = {{ input | json_encode }}
+obj.spoonPath = hs.spoons.scriptPath()
+
+function obj:init()
+  self.icon = hs.image.imageFromPath(self.spoonPath .. "/icon.png")
+  self.errorIcon = hs.image.imageFromPath(self.spoonPath .. "/error.png")
+  self.chooser = hs.chooser.new(function(choice)
+    if choice then
+      if choice.selected then
+        self:select(choice.text)
+      end
+      self:execute(choice.action, choice.arg)
+    end
+  end)
+  self.chooser:placeholderText(self.bin)
+  self.chooser:queryChangedCallback(function(query)
+    -- The binary runs after the typing pauses, the timer is restarted by the next character
+    if self.timer then
+      self.timer:stop()
+    end
+    if self.debounce > 0 then
+      self.timer = hs.timer.doAfter(self.debounce, function()
+        self:search(query)
+      end)
+    else
+      self:search(query)
+    end
+  end)
+  return self
+end
+
+function obj:execute(action, arg)
+  if action == "paste" then
+    hs.eventtap.keyStrokes(arg)
+  elseif action == "url" then
+    hs.urlevent.openURL(arg)
+  elseif action == "open" then
+    hs.open(arg)
+  elseif action == "reveal" then
+    hs.task.new("/usr/bin/open", nil, { "-R", arg }):start()
+  elseif action == "terminal" then
+    hs.osascript.applescript('tell application "Terminal" to do script ' .. string.format("%q", arg))
+  elseif action == "copyfile" then
+    -- The image is pasted as the image, the other files as the path
+    local image = hs.image.imageFromPath(arg)
+    if image then
+      hs.pasteboard.writeObjects(image)
+    else
+      hs.pasteboard.setContents(arg)
+    end
+    hs.alert.show("Added to clipboard")
+  elseif action == "submit" then
+    self:submit(arg)
+  elseif action == "rerun" then
+    self.chooser:query(arg)
+    self.chooser:show()
+  else
+    hs.pasteboard.setContents(arg)
+    hs.alert.show("Added to clipboard")
+  end
+end
+
+-- The notify output runs the binary on the submit only, the result is shown as a notification
+function obj:submit(query)
+  local args = { "launcher", "run", "--launcher", "hammerspoon" }
+  for _, option in ipairs(self.options) do
+    table.insert(args, option)
+  end
+  if self.inputData then
+    table.insert(args, "--stdin")
+  end
+  for _, arg in ipairs({ "--submit", "--", self.bin, query }) do
+    table.insert(args, arg)
+  end
+  local task = hs.task.new(self.shim, function(exitCode, stdOut, stdErr)
+    local result = exitCode == 0 and hs.json.decode(stdOut)
+      or { title = "cargo-launcher failed", message = stdErr, success = false }
+    -- The failure plays the sound and stays until it's closed
+    hs.notify.new({
+      title = result.title,
+      informativeText = result.message,
+      soundName = not result.success and "Basso" or nil,
+      withdrawAfter = result.success and 5 or 0,
+    }):send()
+  end, args)
+  task:start()
+  if self.inputData then
+    task:setInput(self.inputData)
+    task:closeInput()
+  end
+end
+
+-- The selected items are recorded by the shim to rank them higher next time
+function obj:select(title)
+  if not hs.fnutils.contains(self.options, "--history") then
+    return
+  end
+  local args = { "launcher", "run", "--launcher", "hammerspoon" }
+  for _, option in ipairs(self.options) do
+    table.insert(args, option)
+  end
+  for _, arg in ipairs({ "--select", title, "--", self.bin }) do
+    table.insert(args, arg)
+  end
+  hs.task.new(self.shim, nil, args):start()
+end
+
+function obj:search(query)
+  if self.task and self.task:isRunning() then
+    self.task:terminate()
+  end
+
+  -- The shim runs the binary and formats the output, the query is never evaluated by a shell
+  local args = { "launcher", "run", "--launcher", "hammerspoon" }
+  for _, option in ipairs(self.options) do
+    table.insert(args, option)
+  end
+  -- The clipboard or the selection is written to the stdin of the shim
+  if self.inputData then
+    table.insert(args, "--stdin")
+  end
+  table.insert(args, "--")
+  table.insert(args, self.bin)
+  table.insert(args, query)
+
+  local choices = {}
+  local buffer = ""
+  local task
+  task = hs.task.new(self.shim, function(exitCode, stdOut, stdErr)
+    -- Ignore the results of the terminated previous query
+    if task ~= self.task then
+      return
+    end
+    if exitCode == 0 then
+      self:addLines(choices, buffer .. (stdOut or "") .. "\n")
+    else
+      -- The shim reports the failures of the binary as items, this is the failure of the shim itself
+      local details = stdErr:gsub("^%s+", ""):gsub("%s+$", "")
+      table.insert(choices, {
+        text = "cargo-launcher failed",
+        subText = "Exit code " .. exitCode .. ": " .. (details:match("[^\n]*") or ""),
+        image = self.errorIcon,
+        valid = false,
+      })
+    end
+    self.chooser:choices(choices)
+  end, args)
+  -- The streamed results come one item per line, they're shown as they arrive
+  task:setStreamingCallback(function(_, stdOut)
+    if task ~= self.task then
+      return false
+    end
+    buffer = self:addLines(choices, buffer .. stdOut)
+    self.chooser:choices(choices)
+    return true
+  end)
+  self.task = task
+  self.task:start()
+  if self.inputData then
+    self.task:setInput(self.inputData)
+    self.task:closeInput()
+  end
+end
+
+-- The shim prints {"items": [...]} at once, or one item per line when the results are streamed
+-- Returns the incomplete last line
+function obj:addLines(choices, text)
+  local rest = text:match("[^\n]*$")
+  for line in text:sub(1, #text - #rest):gmatch("([^\n]*)\n") do
+    if line:match("%S") then
+      local parsed = hs.json.decode(line)
+      for _, item in ipairs(parsed.items or { parsed }) do
+        table.insert(choices, self:choice(item))
+      end
+    end
+  end
+  return rest
+end
+
+function obj:choice(item)
+  local choice = {
+    text = item.title,
+    arg = item.arg or item.title,
+    subText = item.subtitle or "Success",
+    image = item.icon and hs.image.imageFromPath(item.icon) or self.icon,
+    valid = item.valid,
+    action = item.action or "copy",
+    -- Only the results of the binary are recorded, not the failures and the candidates
+    selected = item.valid,
+  }
+  -- The chooser runs one action per item, a failed item runs its first action, e.g. copy stderr
+  -- and a completion candidate searches again with the completed query
+  local first = item.actions and item.actions[1]
+  if not item.valid and item.autocomplete then
+    choice.valid = true
+    choice.arg = item.autocomplete
+    choice.action = "rerun"
+  elseif not item.valid and first then
+    choice.valid = true
+    choice.arg = first.arg
+    choice.action = first.type or "copy"
+  end
+  return choice
+end
+
+-- Read before the chooser takes the focus, the selection is the one of the frontmost app
+function obj:readInput()
+  if self.input == "stdin-from-clipboard" then
+    return hs.pasteboard.getContents() or ""
+  elseif self.input == "stdin-from-selection" then
+    local element = hs.uielement.focusedElement()
+    return element and element:selectedText() or ""
+  end
+  return nil
+end
+
+function obj:show()
+  self.inputData = self:readInput()
+  self.chooser:query("")
+  self.chooser:show()
+  return self
+end
+
+function obj:bindHotkeys(mapping)
+  local spec = { show = hs.fnutils.partial(self.show, self) }
+  hs.spoons.bindHotkeysToSpec(spec, mapping)
+  return self
+end
+
+return obj
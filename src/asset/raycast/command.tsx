@@ -0,0 +1,338 @@
+import {
+  Action,
+  ActionPanel,
+  Clipboard,
+  Icon,
+  Keyboard,
+  LaunchProps,
+  List,
+  Toast,
+  getPreferenceValues,
+  getSelectedText,
+  showToast,
+} from "@raycast/api";
+import { ChildProcess, execFile, spawn } from "child_process";
+import { useEffect, useRef, useState } from "react";
+
+interface Preferences {
+  binPath: string;
+}
+
+interface Arguments {
+  query?: string;
+}
+
+type ActionKind = "copy" | "paste" | "url" | "open" | "reveal" | "terminal" | "rerun" | "copyfile" | "submit";
+
+interface ItemAction {
+  title: string;
+  arg: string;
+  type: ActionKind;
+}
+
+interface Notification {
+  title: string;
+  message: string;
+  success: boolean;
+}
+
+interface Item {
+  title: string;
+  subtitle?: string;
+  arg?: string;
+  icon?: string;
+  preview?: string;
+  valid: boolean;
+  action?: ActionKind;
+  actions: ItemAction[];
+  autocomplete?: string;
+}
+
+const SHIM = {{ shim | json_encode }};
+const BIN = {{ bin | json_encode }};
+const OPTIONS = {{ options | json_encode }};
+const PREVIEW = {{ preview | json_encode }};
+const DEBOUNCE_MS = {{ debounce_ms }};
+const INPUT = {{ input | json_encode }};
+const ICON = { source: "icon.png" };
+const PREVIEW_SHORTCUT: Keyboard.Shortcut = { modifiers: ["cmd"], key: "y" };
+
+// The shim prints {"items": [...]} at once, or one item per line when the results are streamed
+function parseLines(lines: string[]): Item[] {
+  return lines
+    .filter((line) => line.trim())
+    .flatMap((line) => {
+      const parsed = JSON.parse(line);
+      return parsed.items ?? [parsed];
+    });
+}
+
+// The detail pane renders Markdown, the plain text is shown as a code block
+function previewMarkdown(text: string): string {
+  if (PREVIEW === "markdown") {
+    return text;
+  }
+  const fence = "`".repeat(Math.max(3, ...(text.match(/`+/g) ?? []).map((s) => s.length + 1)));
+  return `${fence}\n${text}\n${fence}`;
+}
+
+function runInTerminal(command: string) {
+  const script = `tell application "Terminal" to do script ${JSON.stringify(command)}`;
+  execFile("osascript", ["-e", script, "-e", 'tell application "Terminal" to activate']);
+}
+
+// The selection is reported by the callback of each action, Raycast has no common one
+function ItemActionView(props: {
+  kind: ActionKind;
+  title?: string;
+  arg: string;
+  onRerun: (query: string) => void;
+  onSelect?: () => void;
+  onSubmit?: (query: string) => void;
+}) {
+  const { kind, title, arg, onSelect } = props;
+  switch (kind) {
+    case "paste":
+      return <Action.Paste title={title} content={arg} onPaste={onSelect} />;
+    case "url":
+      return <Action.OpenInBrowser title={title} url={arg} onOpen={onSelect} />;
+    case "open":
+      return <Action.Open title={title ?? "Open"} target={arg} onOpen={onSelect} />;
+    case "reveal":
+      return <Action.ShowInFinder title={title} path={arg} onShow={onSelect} />;
+    case "terminal":
+      return (
+        <Action
+          title={title ?? "Run in Terminal"}
+          icon={Icon.Terminal}
+          onAction={() => {
+            onSelect?.();
+            runInTerminal(arg);
+          }}
+        />
+      );
+    case "rerun":
+      return (
+        <Action
+          title={title ?? "Search Again"}
+          icon={Icon.MagnifyingGlass}
+          onAction={() => {
+            onSelect?.();
+            props.onRerun(arg);
+          }}
+        />
+      );
+    case "submit":
+      return <Action title={title ?? "Run"} icon={Icon.Play} onAction={() => props.onSubmit?.(arg)} />;
+    case "copyfile": {
+      const content: Clipboard.Content = { file: arg };
+      return <Action.CopyToClipboard title={title ?? "Copy File"} content={content} onCopy={onSelect} />;
+    }
+    default:
+      return <Action.CopyToClipboard title={title} content={arg} onCopy={onSelect} />;
+  }
+}
+
+// The notify output runs the binary on the action only, the result is shown as a toast
+function submit(query: string, binPath: string, input: Promise<string | undefined>) {
+  const stdin = INPUT === "stdin-from-clipboard" || INPUT === "stdin-from-selection" ? ["--stdin"] : [];
+  const args = ["launcher", "run", "--launcher", "raycast", ...OPTIONS, ...stdin, "--submit", "--", binPath || BIN, query];
+  const toast = showToast({ style: Toast.Style.Animated, title: `Running ${BIN}` });
+  const child = spawn(SHIM, args);
+  let stdout = "";
+  child.stdout.on("data", (data) => {
+    stdout += data;
+  });
+  child.stdin.on("error", () => undefined);
+  input.then((text) => child.stdin.end(text ?? ""));
+  child.on("close", async (code) => {
+    const shown = await toast;
+    if (code !== 0) {
+      shown.style = Toast.Style.Failure;
+      shown.title = "cargo-launcher failed";
+      shown.message = `Exit code ${code}`;
+      return;
+    }
+    const result: Notification = JSON.parse(stdout);
+    shown.style = result.success ? Toast.Style.Success : Toast.Style.Failure;
+    shown.title = result.title;
+    shown.message = result.message;
+  });
+}
+
+// Read once on launch, the selection is lost after the command takes the focus
+async function readInput(): Promise<string | undefined> {
+  switch (INPUT) {
+    case "stdin-from-clipboard":
+      return (await Clipboard.readText()) ?? "";
+    case "stdin-from-selection":
+      return getSelectedText().catch(() => "");
+    default:
+      return undefined;
+  }
+}
+
+// The selected items are recorded by the shim to rank them higher next time
+function select(title: string, binPath: string) {
+  if (!OPTIONS.includes("--history")) {
+    return;
+  }
+  const args = ["launcher", "run", "--launcher", "raycast", ...OPTIONS, "--select", title, "--", binPath || BIN];
+  spawn(SHIM, args, { detached: true, stdio: "ignore" }).unref();
+}
+
+export default function Command(props: LaunchProps<{ arguments: Arguments }>) {
+  const { binPath } = getPreferenceValues<Preferences>();
+  const [searchText, setSearchText] = useState(props.arguments.query ?? "");
+  const [items, setItems] = useState<Item[]>([]);
+  const [error, setError] = useState<string>();
+  const [isLoading, setIsLoading] = useState(true);
+  const [isShowingDetail, setIsShowingDetail] = useState(true);
+  const input = useRef(readInput());
+
+  useEffect(() => {
+    setIsLoading(true);
+    setError(undefined);
+    setItems([]);
+    let child: ChildProcess | undefined;
+    // The binary runs after the typing pauses, the timer is cleared by the next search text
+    const timer = setTimeout(() => {
+      child = run();
+    }, DEBOUNCE_MS);
+    return () => {
+      clearTimeout(timer);
+      child?.kill();
+    };
+  }, [searchText]);
+
+  function run() {
+    // The shim runs the binary and formats the output, the query is never evaluated by a shell
+    // The clipboard or the selection is written to the stdin of the shim
+    const stdin = INPUT === "stdin-from-clipboard" || INPUT === "stdin-from-selection" ? ["--stdin"] : [];
+    const args = ["launcher", "run", "--launcher", "raycast", ...OPTIONS, ...stdin, "--", binPath || BIN, searchText];
+    const child = spawn(SHIM, args);
+    // The shim killed by the next search text closes the pipe
+    child.stdin.on("error", () => undefined);
+    input.current.then((text) => child.stdin.end(text ?? ""));
+    let received: Item[] = [];
+    let buffer = "";
+    let stderr = "";
+    // The streamed results come one item per line, they're shown as they arrive
+    const receive = (lines: string[]) => {
+      received = [...received, ...parseLines(lines)];
+      setItems(received);
+    };
+    child.stdout.on("data", (data) => {
+      buffer += data;
+      const lines = buffer.split("\n");
+      buffer = lines.pop() ?? "";
+      receive(lines);
+    });
+    child.stderr.on("data", (data) => {
+      stderr += data;
+    });
+    child.on("error", (err) => {
+      setError(err.message);
+      setIsLoading(false);
+    });
+    child.on("close", (code, signal) => {
+      if (signal) {
+        return;
+      }
+      receive([buffer]);
+      if (code !== 0) {
+        setError(stderr.trim() || `Exit code ${code}`);
+      }
+      setIsLoading(false);
+    });
+    return child;
+  }
+
+  return (
+    <List
+      isLoading={isLoading}
+      searchText={searchText}
+      onSearchTextChange={setSearchText}
+      searchBarPlaceholder={`Arguments for ${BIN}`}
+      isShowingDetail={isShowingDetail && items.length > 0}
+      throttle
+    >
+      {error ? (
+        // The shim reports the failures of the binary as items, this is the failure of the shim itself
+        <List.Item
+          title="cargo-launcher failed"
+          subtitle={error.split("\n")[0]}
+          icon={Icon.XMarkCircle}
+          actions={
+            <ActionPanel>
+              <Action.CopyToClipboard title="Copy Stderr" content={error} />
+            </ActionPanel>
+          }
+        />
+      ) : (
+        items.map((item, index) => {
+          const arg = item.arg ?? item.title;
+          const kind = item.action ?? "copy";
+          return (
+            <List.Item
+              key={index}
+              title={item.title}
+              subtitle={item.subtitle}
+              icon={item.icon ? { source: item.icon } : item.valid ? ICON : Icon.XMarkCircle}
+              detail={<List.Item.Detail markdown={previewMarkdown(item.preview ?? arg)} />}
+              actions={
+                item.valid ? (
+                  <ActionPanel>
+                    <ItemActionView
+                      kind={kind}
+                      arg={arg}
+                      onRerun={setSearchText}
+                      onSelect={() => select(item.title, binPath)}
+                      onSubmit={(query) => submit(query, binPath, input.current)}
+                    />
+                    {kind !== "copy" && <Action.CopyToClipboard content={arg} />}
+                    {kind !== "paste" && <Action.Paste content={arg} />}
+                    {item.actions.map((action, i) => (
+                      <ItemActionView
+                        key={i}
+                        kind={action.type}
+                        title={action.title}
+                        arg={action.arg}
+                        onRerun={setSearchText}
+                      />
+                    ))}
+                    <Action
+                      title="Toggle Preview"
+                      icon={Icon.Sidebar}
+                      shortcut={PREVIEW_SHORTCUT}
+                      onAction={() => setIsShowingDetail(!isShowingDetail)}
+                    />
+                  </ActionPanel>
+                ) : (
+                  <ActionPanel>
+                    {item.autocomplete !== undefined && (
+                      <Action
+                        title="Autocomplete"
+                        icon={Icon.Text}
+                        onAction={() => setSearchText(item.autocomplete ?? "")}
+                      />
+                    )}
+                    {item.actions.map((action, i) => (
+                      <ItemActionView
+                        key={i}
+                        kind={action.type}
+                        title={action.title}
+                        arg={action.arg}
+                        onRerun={setSearchText}
+                      />
+                    ))}
+                  </ActionPanel>
+                )
+              }
+            />
+          );
+        })
+      )}
+    </List>
+  );
+}
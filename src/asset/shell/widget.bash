@@ -0,0 +1,20 @@
+# {{name}} {{version}}: {{description}}
+# Generated by cargo-launcher
+#
+# Add the following line to ~/.bashrc
+#   source {{path}}
+#
+# Press Ctrl-X Ctrl-L to run {{name}} and insert the output into the command line.
+
+__{{func}}_widget() {
+  local args out
+  read -r -e -p "{{name}}> " args </dev/tty || return
+  out=$({{shim}} launcher run --launcher shell {{options}} -- {{bin}} "$args") || return
+  if [ "$(printf '%s\n' "$out" | wc -l)" -gt 1 ] && command -v fzf >/dev/null 2>&1; then
+    out=$(printf '%s\n' "$out" | fzf --height 40% --reverse --prompt "{{name}}> ") || return
+  fi
+  READLINE_LINE="${READLINE_LINE:0:$READLINE_POINT}$out${READLINE_LINE:$READLINE_POINT}"
+  {% raw %}READLINE_POINT=$((READLINE_POINT + ${#out})){% endraw %}
+}
+
+bind -x '"\C-x\C-l": __{{func}}_widget'
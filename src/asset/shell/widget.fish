@@ -0,0 +1,27 @@
+# {{name}} {{version}}: {{description}}
+# Generated by cargo-launcher
+#
+# Add the following line to ~/.config/fish/config.fish
+#   source {{path}}
+#
+# Press Ctrl-X Ctrl-L to run {{name}} and insert the output into the command line.
+
+function __{{func}}_widget
+    read -l -P '{{name}}> ' args
+    or begin
+        commandline -f repaint
+        return
+    end
+    set -l out ({{shim}} launcher run --launcher shell {{options}} -- {{bin}} "$args")
+    or begin
+        commandline -f repaint
+        return
+    end
+    if test (count $out) -gt 1; and type -q fzf
+        set out (printf '%s\n' $out | fzf --height 40% --reverse --prompt '{{name}}> ')
+    end
+    commandline -i -- (string join \n -- $out)
+    commandline -f repaint
+end
+
+bind \cx\cl __{{func}}_widget
@@ -0,0 +1,81 @@
+const MARKER: &str = "cargo-launcher";
+
+fn begin_marker(id: &str) -> String {
+    format!("# >>> {}: {} >>>", MARKER, id)
+}
+
+fn end_marker(id: &str) -> String {
+    format!("# <<< {}: {} <<<", MARKER, id)
+}
+
+// Replace the managed block written by the previous install, or append it when not found
+pub fn upsert(contents: &str, id: &str, body: &str) -> String {
+    let begin = begin_marker(id);
+    let end = end_marker(id);
+    let block = format!("{}\n{}\n{}\n", begin, body.trim_end(), end);
+
+    match (contents.find(&begin), contents.find(&end)) {
+        (Some(start), Some(stop)) if start < stop => {
+            let tail = &contents[stop + end.len()..];
+            let tail = tail.strip_prefix('\n').unwrap_or(tail);
+            format!("{}{}{}", &contents[..start], block, tail)
+        }
+        _ => {
+            let mut buf = contents.to_owned();
+            if !buf.is_empty() {
+                if !buf.ends_with('\n') {
+                    buf.push('\n');
+                }
+                buf.push('\n');
+            }
+            buf.push_str(&block);
+            buf
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use crate::block::*;
+
+    #[test]
+    fn upsert_empty_ok() {
+        let expected = "# >>> cargo-launcher: test >>>\nbody\n# <<< cargo-launcher: test <<<\n";
+        let actual = upsert("", "test", "body\n");
+        assert_eq!(expected, actual);
+    }
+
+    #[test]
+    fn upsert_append_ok() {
+        let contents = "[general]\nkey = 1";
+        let expected = "[general]\nkey = 1\n\n# >>> cargo-launcher: test >>>\nbody\n# <<< cargo-launcher: test <<<\n";
+        let actual = upsert(contents, "test", "body");
+        assert_eq!(expected, actual);
+    }
+
+    #[test]
+    fn upsert_replace_ok() {
+        let contents =
+            "a\n# >>> cargo-launcher: test >>>\nold\n# <<< cargo-launcher: test <<<\nb\n";
+        let expected =
+            "a\n# >>> cargo-launcher: test >>>\nnew\n# <<< cargo-launcher: test <<<\nb\n";
+        let actual = upsert(contents, "test", "new");
+        assert_eq!(expected, actual);
+    }
+
+    #[test]
+    fn upsert_idempotent_ok() {
+        let once = upsert("[general]\n", "test", "body");
+        let twice = upsert(&once, "test", "body");
+        assert_eq!(once, twice);
+    }
+
+    #[test]
+    fn upsert_other_id_ok() {
+        let once = upsert("", "other", "body");
+        let actual = upsert(&once, "test", "body");
+        assert!(actual.contains("cargo-launcher: other"));
+        assert!(actual.contains("cargo-launcher: test"));
+    }
+}
@@ -0,0 +1,193 @@
+use log::*;
+use serde_derive::*;
+use serde_json::json;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+use crate::core::hash;
+use crate::error::Result;
+use crate::fs::{mk_dir, read_file, write_file};
+use crate::item::Item;
+
+pub const ERROR_ICON: &[u8] = include_bytes!("asset/error.png");
+// The log is rotated to .old when it's over the size, so at most two files are kept
+const LOG_MAX_BYTES: u64 = 1024 * 1024;
+
+// The files generated at runtime live under the user's cache dir, e.g. ~/.cache/cargo-launcher/<name>
+pub fn dir(name: &str) -> Result<PathBuf> {
+    let mut path = root()?;
+    path.push(name);
+    mk_dir(&path)?;
+    Ok(path)
+}
+
+#[cfg(not(test))]
+fn root() -> Result<PathBuf> {
+    let mut path = dirs::cache_dir().ok_or_else(|| failure::err_msg("Notfound cache dir"))?;
+    path.push("cargo-launcher");
+    Ok(path)
+}
+
+// One temporary dir per test thread, it's removed when the test ends
+#[cfg(test)]
+fn root() -> Result<PathBuf> {
+    thread_local! {
+        static ROOT: tempdir::TempDir = tempdir::TempDir::new("cargo-launcher-cache").unwrap();
+    }
+    Ok(ROOT.with(|root| root.path().to_owned()))
+}
+
+// Launchers need the icon as a file, it's written once and shared by all plugins
+pub fn error_icon() -> Result<PathBuf> {
+    let mut path = dir("icon")?;
+    path.push("error.png");
+    if !path.exists() {
+        write_file(&path, ERROR_ICON)?;
+    }
+    Ok(path)
+}
+
+pub fn log_path(name: &str) -> Result<PathBuf> {
+    let mut path = dir("log")?;
+    path.push(format!("{}.log", name));
+    Ok(path)
+}
+
+pub fn append_log(name: &str, entry: &str) -> Result<PathBuf> {
+    let path = log_path(name)?;
+    rotate(&path)?;
+    let mut file = OpenOptions::new().create(true).append(true).open(&path)?;
+    writeln!(file, "{}", entry.trim_end())?;
+    Ok(path)
+}
+
+// One file per query, the key is kept in the file to tell the hash collisions
+#[derive(Deserialize)]
+struct Entry {
+    key: String,
+    items: Vec<Item>,
+}
+
+// The results are shared by the launchers of the same binary, e.g. ~/.cache/cargo-launcher/result/<bin>
+pub fn result_dir(name: &str) -> Result<PathBuf> {
+    dir(&format!("result/{}", name))
+}
+
+// Returns None when the entry is missing, expired or broken
+pub fn read_items(dir: &Path, key: &str, ttl: Duration) -> Option<Vec<Item>> {
+    let path = entry_path(dir, key);
+    let age = fs::metadata(&path)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+        .and_then(|modified| SystemTime::now().duration_since(modified).ok())?;
+    if age > ttl {
+        let _ = fs::remove_file(&path);
+        return None;
+    }
+    read_file(&path)
+        .ok()
+        .and_then(|data| serde_json::from_slice::<Entry>(&data).ok())
+        .filter(|entry| entry.key == key)
+        .map(|entry| entry.items)
+}
+
+// The oldest entries are removed over the max entries
+pub fn write_items(dir: &Path, key: &str, items: &[Item], max_entries: usize) -> Result<()> {
+    let entry = json!({ "key": key, "items": items });
+    // Renamed at once, the other launchers never read a half written entry
+    let path = entry_path(dir, key);
+    let tmp = path.with_extension("tmp");
+    write_file(&tmp, serde_json::to_string(&entry)?.as_bytes())?;
+    fs::rename(&tmp, &path)?;
+
+    let mut entries = fs::read_dir(dir)?
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "json"))
+        .filter_map(|path| Some((fs::metadata(&path).ok()?.modified().ok()?, path)))
+        .collect::<Vec<_>>();
+    if entries.len() > max_entries {
+        entries.sort();
+        for (_, path) in &entries[..entries.len() - max_entries] {
+            debug!("evict: {:?}", path);
+            fs::remove_file(path)?;
+        }
+    }
+    Ok(())
+}
+
+fn entry_path(dir: &Path, key: &str) -> PathBuf {
+    dir.join(format!("{}.json", hash(key)))
+}
+
+fn rotate(path: &Path) -> Result<()> {
+    let size = match fs::metadata(path) {
+        Ok(metadata) => metadata.len(),
+        Err(_) => return Ok(()),
+    };
+    if size > LOG_MAX_BYTES {
+        let mut old = path.as_os_str().to_owned();
+        old.push(".old");
+        fs::rename(path, old)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+
+    use crate::cache::*;
+    use std::env;
+    use std::thread;
+    use tempdir::TempDir;
+
+    #[test]
+    fn error_icon_ok() {
+        let actual = error_icon().unwrap();
+        assert!(actual.starts_with(env::temp_dir()));
+        assert_eq!(read_file(&actual).unwrap(), ERROR_ICON);
+    }
+
+    #[test]
+    fn append_log_ok() {
+        let name = "cargo-launcher-test-append";
+        let path = log_path(name).unwrap();
+        assert!(path.starts_with(env::temp_dir()));
+
+        append_log(name, "first\n").unwrap();
+        append_log(name, "second").unwrap();
+        let actual = read_file(&path).unwrap();
+        assert_eq!(actual, b"first\nsecond\n");
+    }
+
+    #[test]
+    fn read_items_ok() {
+        let tmp_dir = TempDir::new("read_items_ok").unwrap();
+        let dir = tmp_dir.path();
+        let ttl = Duration::from_secs(60);
+        assert_eq!(read_items(dir, "a", ttl), None);
+
+        write_items(dir, "a", &[Item::text("1")], 10).unwrap();
+        assert_eq!(read_items(dir, "a", ttl), Some(vec![Item::text("1")]));
+        assert_eq!(read_items(dir, "b", ttl), None);
+        assert_eq!(read_items(dir, "a", Duration::from_secs(0)), None);
+        assert!(!entry_path(dir, "a").exists());
+    }
+
+    #[test]
+    fn write_items_evict_ok() {
+        let tmp_dir = TempDir::new("write_items_evict_ok").unwrap();
+        let dir = tmp_dir.path();
+        let ttl = Duration::from_secs(60);
+        for key in &["a", "b", "c"] {
+            write_items(dir, key, &[Item::text(key)], 2).unwrap();
+            // The entries are ordered by the modified time
+            thread::sleep(Duration::from_millis(20));
+        }
+        assert_eq!(read_items(dir, "a", ttl), None);
+        assert_eq!(read_items(dir, "b", ttl), Some(vec![Item::text("b")]));
+        assert_eq!(read_items(dir, "c", ttl), Some(vec![Item::text("c")]));
+    }
+}
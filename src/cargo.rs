@@ -1,8 +1,17 @@
 use serde_derive::*;
+use std::collections::BTreeMap;
 use std::path::PathBuf;
 
 use crate::core::*;
 use crate::error::Result;
+use crate::input::Input;
+use crate::item::ActionKind;
+use crate::notify::OutputMode;
+use crate::preview::PreviewFormat;
+use crate::server::Mode;
+use crate::shim::Tokenize;
+use crate::subcommand::Subcommand;
+use crate::usage::EmptyQuery;
 
 #[derive(Serialize, Deserialize)]
 pub struct CargoConfig {
@@ -11,6 +20,8 @@ pub struct CargoConfig {
     description: Option<String>,
     icon: Option<PathBuf>,
     authors: Option<Vec<String>>,
+    manifest_path: Option<PathBuf>,
+    metadata: Option<Metadata>,
 }
 
 impl CargoConfig {
@@ -21,7 +32,7 @@ impl CargoConfig {
         self.version.as_str()
     }
     pub fn description(&self) -> &str {
-        self.description.as_ref().map(|d| d.as_str()).unwrap_or("")
+        self.description.as_deref().unwrap_or("")
     }
 
     pub fn author(&self) -> String {
@@ -34,16 +45,202 @@ impl CargoConfig {
     pub fn build_id(&self) -> String {
         format!("{}-{}", self.name(), hash(self.name()))
     }
+
+    pub fn tokenize(&self) -> Tokenize {
+        self.launcher().map(|l| l.tokenize).unwrap_or_default()
+    }
+
+    // Options passed to the shim before the binary, they are the same for every launcher
+    pub fn run_options(&self) -> Vec<String> {
+        self.run_options_with(self.description())
+    }
+
+    // The description is the one of the keyword, it's shown on the empty query
+    pub fn run_options_with(&self, description: &str) -> Vec<String> {
+        let mut options = vec![
+            String::from("--tokenize"),
+            self.tokenize().as_str().to_owned(),
+        ];
+        let launcher = match self.launcher() {
+            Some(launcher) => launcher,
+            None => return options,
+        };
+        for (key, value) in &launcher.env {
+            options.push(String::from("--env"));
+            options.push(format!("{}={}", key, value));
+        }
+        if let Some(cwd) = self.cwd() {
+            options.push(String::from("--cwd"));
+            options.push(cwd.to_string_lossy().into_owned());
+        }
+        if let Some(timeout_ms) = launcher.timeout_ms {
+            options.push(String::from("--timeout-ms"));
+            options.push(timeout_ms.to_string());
+        }
+        if launcher.input != Input::default() {
+            options.push(String::from("--input"));
+            options.push(launcher.input.as_str().to_owned());
+        }
+        if let Some(action) = launcher.action {
+            options.push(String::from("--action"));
+            options.push(action.as_str().to_owned());
+        }
+        if launcher.preview.is_some() {
+            options.push(String::from("--preview"));
+            options.push(self.preview().as_str().to_owned());
+        }
+        if launcher.mode != Mode::default() {
+            options.push(String::from("--mode"));
+            options.push(launcher.mode.as_str().to_owned());
+        }
+        if launcher.output != OutputMode::default() {
+            options.push(String::from("--output"));
+            options.push(launcher.output.as_str().to_owned());
+        }
+        if launcher.stream {
+            options.push(String::from("--stream"));
+        }
+        let empty_query = launcher.empty_query();
+        if empty_query != EmptyQuery::default() {
+            options.push(String::from("--empty-query"));
+            options.push(empty_query.as_str().to_owned());
+        }
+        if empty_query == EmptyQuery::Usage && !description.is_empty() {
+            options.push(String::from("--description"));
+            options.push(description.to_owned());
+        }
+        if launcher.complete {
+            options.push(String::from("--complete"));
+        }
+        if launcher.history {
+            options.push(String::from("--history"));
+        }
+        if let Some(cache) = &launcher.cache {
+            options.push(String::from("--cache-ttl-secs"));
+            options.push(cache.ttl_secs.to_string());
+            options.push(String::from("--cache-max-entries"));
+            options.push(cache.max_entries.to_string());
+        }
+        options
+    }
+
+    // The plugins record the selected items only when the history is kept
+    pub fn history(&self) -> bool {
+        self.launcher().is_some_and(|l| l.history)
+    }
+
+    // Embedded into the plugins, they read the clipboard or the selection by their API
+    pub fn input(&self) -> Input {
+        self.launcher().map(|l| l.input).unwrap_or_default()
+    }
+
+    // The plugins run the binary on the action only, the result is notified
+    pub fn output(&self) -> OutputMode {
+        self.launcher().map(|l| l.output).unwrap_or_default()
+    }
+
+    pub fn mode(&self) -> Mode {
+        self.launcher().map(|l| l.mode).unwrap_or_default()
+    }
+
+    // Embedded into the plugins, the launchers wait for it after the last typed character
+    pub fn debounce_ms(&self) -> u64 {
+        self.launcher().and_then(|l| l.debounce_ms).unwrap_or(0)
+    }
+
+    pub fn preview(&self) -> PreviewFormat {
+        self.launcher().and_then(|l| l.preview).unwrap_or_default()
+    }
+
+    pub fn commands(&self) -> &[Subcommand] {
+        self.launcher().map(|l| &l.commands[..]).unwrap_or(&[])
+    }
+
+    // A relative cwd is resolved from the directory of Cargo.toml
+    fn cwd(&self) -> Option<PathBuf> {
+        let cwd = self.launcher()?.cwd.as_ref()?;
+        let dir = self.manifest_path.as_ref().and_then(|path| path.parent());
+        match dir {
+            Some(dir) if cwd.is_relative() => Some(dir.join(cwd)),
+            _ => Some(cwd.clone()),
+        }
+    }
+
+    fn launcher(&self) -> Option<&LauncherConfig> {
+        self.metadata.as_ref().and_then(|m| m.launcher.as_ref())
+    }
+}
+
+#[cfg(test)]
+impl CargoConfig {
+    pub fn dummy(name: &str) -> CargoConfig {
+        CargoConfig {
+            name: name.to_owned(),
+            version: String::from("0.1.0"),
+            description: Some(String::from("Test description")),
+            icon: None,
+            authors: Some(vec![String::from("watawuwu")]),
+            manifest_path: None,
+            metadata: None,
+        }
+    }
 }
 
 #[derive(Serialize, Deserialize)]
 pub struct Metadata {
-    launcher: LauncherConfig,
+    launcher: Option<LauncherConfig>,
 }
 
 #[derive(Serialize, Deserialize)]
 pub struct LauncherConfig {
     icon: Option<PathBuf>,
+    #[serde(default)]
+    tokenize: Tokenize,
+    #[serde(default)]
+    env: BTreeMap<String, String>,
+    cwd: Option<PathBuf>,
+    timeout_ms: Option<u64>,
+    #[serde(default)]
+    input: Input,
+    action: Option<ActionKind>,
+    preview: Option<PreviewFormat>,
+    #[serde(default)]
+    mode: Mode,
+    #[serde(default)]
+    output: OutputMode,
+    #[serde(default)]
+    stream: bool,
+    debounce_ms: Option<u64>,
+    empty_query: Option<EmptyQuery>,
+    #[serde(default)]
+    complete: bool,
+    #[serde(default)]
+    history: bool,
+    cache: Option<CacheConfig>,
+    #[serde(default)]
+    commands: Vec<Subcommand>,
+}
+
+impl LauncherConfig {
+    // The recent queries are suggested by default when the history is kept
+    fn empty_query(&self) -> EmptyQuery {
+        match self.empty_query {
+            Some(empty_query) => empty_query,
+            None if self.history => EmptyQuery::Recent,
+            None => EmptyQuery::default(),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct CacheConfig {
+    ttl_secs: u64,
+    #[serde(default = "default_max_entries")]
+    max_entries: usize,
+}
+
+fn default_max_entries() -> usize {
+    100
 }
 
 fn cargo_exec(sub: Vec<&str>) -> Result<String> {
@@ -55,7 +252,7 @@ pub fn config(opt_path: &Option<PathBuf>, name: Option<&str>) -> Result<CargoCon
     let mut args = vec!["read-manifest"];
 
     if let Some(path) = opt_path {
-        if let Some(ref s) = path.to_str() {
+        if let Some(s) = path.to_str() {
             args.push("--manifest-path");
             args.push(s);
         }
@@ -93,6 +290,35 @@ license     = "MIT"
 description = "Test description"
 repository  = "https://github.com/watawuwu/cargo-launcher"
 readme      = "README.md"
+"##;
+
+    const DUMMY_METADATA: &str = r##"
+[package.metadata.launcher]
+tokenize    = "shell"
+cwd         = "data"
+timeout_ms  = 3000
+input       = "stdin-from-clipboard"
+action      = "url"
+preview     = "markdown"
+mode        = "server"
+output      = "notify"
+stream      = true
+debounce_ms = 200
+complete    = true
+empty_query = "usage"
+history     = true
+
+[package.metadata.launcher.cache]
+ttl_secs = 300
+
+[package.metadata.launcher.env]
+RUST_LOG = "off"
+API_URL  = "http://localhost:8080/?a=b"
+
+[[package.metadata.launcher.commands]]
+keyword     = "test-encode"
+description = "Encode the input"
+args        = ["encode", "--base64"]
 "##;
 
     const DUMMY_MAIN: &str = r##"fn main() { println!("test"); }"##;
@@ -132,4 +358,66 @@ readme      = "README.md"
         assert_eq!(name, expected);
     }
 
+    #[test]
+    fn config_metadata_ok() {
+        let tmp_dir = TempDir::new("config_metadata_ok").unwrap();
+        let toml = format!("{}{}", DUMMY_CARGO, DUMMY_METADATA);
+        let cargo_file = create_tmp_project(&tmp_dir, &toml);
+        let cargo = config(&Some(cargo_file), None).unwrap();
+        assert_eq!(cargo.tokenize(), Tokenize::Shell);
+
+        let cwd = tmp_dir.path().join("data").to_string_lossy().into_owned();
+        let expected = vec![
+            "--tokenize",
+            "shell",
+            "--env",
+            "API_URL=http://localhost:8080/?a=b",
+            "--env",
+            "RUST_LOG=off",
+            "--cwd",
+            cwd.as_str(),
+            "--timeout-ms",
+            "3000",
+            "--input",
+            "stdin-from-clipboard",
+            "--action",
+            "url",
+            "--preview",
+            "markdown",
+            "--mode",
+            "server",
+            "--output",
+            "notify",
+            "--stream",
+            "--empty-query",
+            "usage",
+            "--description",
+            "Test description",
+            "--complete",
+            "--history",
+            "--cache-ttl-secs",
+            "300",
+            "--cache-max-entries",
+            "100",
+        ];
+        assert_eq!(cargo.run_options(), expected);
+        assert_eq!(cargo.debounce_ms(), 200);
+        assert_eq!(cargo.mode(), Mode::Server);
+        assert!(cargo.history());
+        assert_eq!(cargo.output(), OutputMode::Notify);
+        assert_eq!(cargo.input(), Input::StdinFromClipboard);
+        assert_eq!(cargo.commands()[0].keyword, "test-encode");
+        assert_eq!(cargo.commands()[0].args, vec!["encode", "--base64"]);
+    }
+
+    #[test]
+    fn config_metadata_none_ok() {
+        let tmp_dir = TempDir::new("config_metadata_none_ok").unwrap();
+        let cargo_file = create_tmp_project(&tmp_dir, DUMMY_CARGO);
+        let cargo = config(&Some(cargo_file), None).unwrap();
+        assert_eq!(cargo.tokenize(), Tokenize::Whitespace);
+        assert_eq!(cargo.run_options(), vec!["--tokenize", "whitespace"]);
+        assert_eq!(cargo.debounce_ms(), 0);
+        assert!(cargo.commands().is_empty());
+    }
 }
@@ -0,0 +1,131 @@
+use log::*;
+use std::time::Duration;
+
+use crate::args::RunArgs;
+use crate::item::Item;
+use crate::shim::{self, Tokenize};
+
+// The binary prints the candidates of the last word, e.g. `tool --launcher-complete encode --ba`
+const COMPLETE_FLAG: &str = "--launcher-complete";
+// The completion runs before the results on every query, it has to be quick
+const COMPLETE_TIMEOUT: Duration = Duration::from_secs(1);
+const COMPLETE_SUBTITLE: &str = "Complete";
+
+// The candidates are shown as invalid items, the autocomplete replaces the last word by them
+pub fn candidates(args: &RunArgs, query: &[String]) -> Vec<Item> {
+    let mut words = query.to_vec();
+    // The next word is completed after the space
+    let joined = args.query.join(" ");
+    if joined.is_empty() || joined.ends_with(char::is_whitespace) {
+        words.push(String::new());
+    }
+    let mut cmd = shim::base_command(args);
+    cmd.args(&args.prefix_args).arg(COMPLETE_FLAG).args(&words);
+    let timeout = args
+        .timeout_ms
+        .map_or(COMPLETE_TIMEOUT, Duration::from_millis);
+    match shim::exec(cmd, Some(timeout)) {
+        Ok(output) if output.success() => parse(&output.stdout)
+            .into_iter()
+            .map(|(candidate, description)| {
+                let autocomplete = completed(&words, candidate, args.tokenize);
+                Item {
+                    subtitle: Some(description.unwrap_or(COMPLETE_SUBTITLE).to_owned()),
+                    valid: false,
+                    autocomplete: Some(autocomplete),
+                    ..Item::text(candidate)
+                }
+            })
+            .collect(),
+        Ok(output) => {
+            debug!("complete: {}", output.summary());
+            vec![]
+        }
+        Err(err) => {
+            debug!("complete: {}", err);
+            vec![]
+        }
+    }
+}
+
+// One candidate per line, the description follows a tab as the fish completions
+fn parse(stdout: &str) -> Vec<(&str, Option<&str>)> {
+    stdout
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| match line.find('\t') {
+            Some(pos) => (&line[..pos], Some(line[pos + 1..].trim())),
+            None => (line, None),
+        })
+        .collect()
+}
+
+// The space is added to type the next word, except after the separators of paths and values
+// The whole query is one argument, so it's replaced as is
+fn completed(words: &[String], candidate: &str, tokenize: Tokenize) -> String {
+    let mut words = words[..words.len() - 1].to_vec();
+    words.push(candidate.to_owned());
+    let query = match tokenize {
+        Tokenize::Whole => return candidate.to_owned(),
+        Tokenize::Whitespace => words.join(" "),
+        Tokenize::Shell => shell_words::join(&words),
+    };
+    if candidate.ends_with(&['/', '='][..]) {
+        query
+    } else {
+        format!("{} ", query)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use crate::complete::*;
+
+    fn words(words: &[&str]) -> Vec<String> {
+        words.iter().map(|word| String::from(*word)).collect()
+    }
+
+    #[test]
+    fn parse_ok() {
+        let actual = parse("encode\tEncode the input\ndecode\n\n");
+        assert_eq!(
+            actual,
+            vec![("encode", Some("Encode the input")), ("decode", None)]
+        );
+    }
+
+    #[test]
+    fn completed_ok() {
+        let w = words(&["encode", "--ba"]);
+        assert_eq!(
+            completed(&w, "--base64", Tokenize::Whitespace),
+            "encode --base64 "
+        );
+        assert_eq!(
+            completed(&w, "--out=", Tokenize::Whitespace),
+            "encode --out="
+        );
+        let w = words(&["open", "my"]);
+        assert_eq!(
+            completed(&w, "my file.txt", Tokenize::Shell),
+            "open 'my file.txt' "
+        );
+        assert_eq!(completed(&w, "my file", Tokenize::Whole), "my file");
+    }
+
+    #[test]
+    #[cfg(not(target_os = "windows"))]
+    fn candidates_ok() {
+        let mut args = RunArgs::dummy("sh");
+        args.prefix_args = words(&["-c", r#"printf '%s\t%s\n' "[$2]" sub"#, "sh"]);
+        args.query = words(&["a "]);
+        let actual = candidates(&args, &words(&["a"]));
+        assert_eq!(actual.len(), 1);
+        // $1 is the flag, $2 is the first word
+        assert_eq!(actual[0].title, "[a]");
+        assert_eq!(actual[0].subtitle.as_deref(), Some("sub"));
+        assert_eq!(actual[0].autocomplete.as_deref(), Some("a [a] "));
+        assert!(!actual[0].valid);
+    }
+}
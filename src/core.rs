@@ -5,7 +5,7 @@ use std::hash::Hasher;
 use std::process::Command;
 
 pub fn command<P: AsRef<OsStr>>(program: P, maybe_args: Option<Vec<P>>) -> Result<String> {
-    let args = maybe_args.unwrap_or_else(|| vec![]);
+    let args = maybe_args.unwrap_or_default();
     let output = Command::new(program).args(args).output()?;
 
     let result = String::from_utf8_lossy(&output.stdout).into_owned();
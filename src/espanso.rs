@@ -0,0 +1,131 @@
+use failure::*;
+use log::*;
+use std::fs;
+use std::path::PathBuf;
+
+use crate::cargo::CargoConfig;
+use crate::error::Result;
+use crate::fs::write_file;
+use crate::launcher::{LauncherConfig, LauncherLike};
+use crate::tpl::{self, Param};
+
+const PACKAGE_YML_BIN: &[u8] = include_bytes!("asset/espanso/package.yml");
+const MANIFEST_YML_BIN: &[u8] = include_bytes!("asset/espanso/_manifest.yml");
+
+pub struct Espanso<'a> {
+    cargo_config: &'a CargoConfig,
+    launcher_config: &'a LauncherConfig<'a>,
+}
+
+impl<'a> Espanso<'a> {
+    pub fn new(cargo_config: &'a CargoConfig, launcher_config: &'a LauncherConfig) -> Espanso<'a> {
+        Espanso {
+            cargo_config,
+            launcher_config,
+        }
+    }
+
+    fn application_config(&self) -> Result<PathBuf> {
+        let mut path = match std::env::var_os("XDG_CONFIG_HOME") {
+            Some(home) => PathBuf::from(home),
+            None => {
+                let mut home = dirs::home_dir().ok_or_else(|| err_msg("Notfound home dir"))?;
+                home.push(".config");
+                home
+            }
+        };
+        path.push("espanso/match/packages");
+        path.push(self.cargo_config.name());
+        Ok(path)
+    }
+
+    fn package_yml_path(&self) -> PathBuf {
+        let mut buf = self.launcher_config.work_dir.clone();
+        buf.push("package.yml");
+        buf
+    }
+
+    fn manifest_yml_path(&self) -> PathBuf {
+        let mut buf = self.launcher_config.work_dir.clone();
+        buf.push("_manifest.yml");
+        buf
+    }
+
+    fn package_yml(&self) -> Result<String> {
+        let name = self.cargo_config.name();
+        let mut params = Param::new();
+        params.insert("trigger", &format!(":{}", name));
+        params.insert("layout", &format!("{} [[args]]", name));
+        // The form value is injected as one argument, it's never evaluated by a shell
+        let shim = self.launcher_config.shim();
+        let bin = self.launcher_config.bin();
+        let mut args = vec![shim.as_str(), "launcher", "run", "--launcher", "espanso"];
+        let options = self.cargo_config.run_options();
+        args.extend(options.iter().map(String::as_str));
+        args.extend(&["--", bin.as_str(), "{{form1.args}}"]);
+        params.insert("args", &args);
+
+        let tpl = String::from_utf8_lossy(PACKAGE_YML_BIN).into_owned();
+        let contents = tpl::render_raw(&tpl, &params)?;
+
+        Ok(contents)
+    }
+
+    fn manifest_yml(&self) -> Result<String> {
+        let conf = self.cargo_config;
+        let mut params = Param::new();
+        params.insert("name", conf.name());
+        params.insert("version", conf.version());
+        params.insert("description", conf.description());
+        params.insert("author", &conf.author());
+
+        let tpl = String::from_utf8_lossy(MANIFEST_YML_BIN).into_owned();
+        let contents = tpl::render_raw(&tpl, &params)?;
+
+        Ok(contents)
+    }
+}
+
+impl<'a> LauncherLike for Espanso<'a> {
+    fn before_check(&self) -> Result<()> {
+        Ok(())
+    }
+
+    fn gen(&self) -> Result<Vec<PathBuf>> {
+        let package = self.package_yml_path();
+        write_file(&package, self.package_yml()?.as_bytes())?;
+
+        let manifest = self.manifest_yml_path();
+        write_file(&manifest, self.manifest_yml()?.as_bytes())?;
+
+        Ok(vec![package, manifest])
+    }
+
+    fn deploy(&self, paths: Vec<PathBuf>) -> Result<()> {
+        let sink_dir = self.application_config()?;
+        fs::create_dir_all(&sink_dir)?;
+        for path in paths {
+            debug!("path: {:?}", &path);
+            debug!("sink: {:?}", &sink_dir);
+            let name = path.file_name().ok_or_else(|| err_msg("Not file type"))?;
+            let mut sink = sink_dir.clone();
+            sink.push(name);
+            fs::copy(&path, sink)?;
+        }
+        Ok(())
+    }
+
+    fn completed_message(&self) -> Result<String> {
+        let msg = format!(
+            r#"
+Install completed!!
+Type ":{}" in any text field to expand the output.
+Restart of the espanso may be required.
+
+Installed path: "#,
+            self.cargo_config.name()
+        );
+        let path = self.application_config()?;
+        Ok(format!("{}{}", msg, path.to_string_lossy()))
+    }
+}
@@ -0,0 +1,125 @@
+use std::cmp::Reverse;
+
+use crate::item::Item;
+
+// The matched char right after the previous one, or at the start of a word, ranks higher
+const CONSECUTIVE_BONUS: i64 = 5;
+const WORD_START_BONUS: i64 = 3;
+const MAX_GAP_PENALTY: i64 = 3;
+
+// Keeps the items whose title matches every word of the query, the best matches first
+pub fn filter(items: Vec<Item>, query: &str) -> Vec<Item> {
+    let words = query.split_whitespace().collect::<Vec<&str>>();
+    if words.is_empty() {
+        return items;
+    }
+    let mut scored = items
+        .into_iter()
+        .filter_map(|item| {
+            let score = words
+                .iter()
+                .map(|word| score(word, &item.title))
+                .sum::<Option<i64>>()?;
+            Some((score, item))
+        })
+        .collect::<Vec<(i64, Item)>>();
+    // The sort is stable, the items of the same score keep the order of the binary
+    scored.sort_by_key(|(score, _)| Reverse(*score));
+    scored.into_iter().map(|(_, item)| item).collect()
+}
+
+// The chars of the pattern have to be found in order in the text, case insensitive
+// Every position of the first char is tried as start, the best one is taken
+fn score(pattern: &str, text: &str) -> Option<i64> {
+    let text = text.to_lowercase().chars().collect::<Vec<char>>();
+    let pattern = pattern.to_lowercase().chars().collect::<Vec<char>>();
+    let first = *pattern.first()?;
+    (0..text.len())
+        .filter(|&start| text[start] == first)
+        .filter_map(|start| score_from(&pattern, &text, start))
+        .max()
+}
+
+fn score_from(pattern: &[char], text: &[char], start: usize) -> Option<i64> {
+    let mut score = 0;
+    let mut pos = start;
+    let mut prev: Option<usize> = None;
+    for &c in pattern {
+        let found = pos + text[pos..].iter().position(|&t| t == c)?;
+        score += 1;
+        if prev.is_some_and(|prev| prev + 1 == found) {
+            score += CONSECUTIVE_BONUS;
+        }
+        if found == 0 || !text[found - 1].is_alphanumeric() {
+            score += WORD_START_BONUS;
+        }
+        // The distance to the start isn't a penalty, only the gaps between the chars
+        if prev.is_some() {
+            score -= ((found - pos) as i64).min(MAX_GAP_PENALTY);
+        }
+        prev = Some(found);
+        pos = found + 1;
+    }
+    Some(score)
+}
+
+#[cfg(test)]
+mod tests {
+
+    use crate::filter::*;
+
+    fn titles(items: &[Item]) -> Vec<&str> {
+        items.iter().map(|item| item.title.as_str()).collect()
+    }
+
+    #[test]
+    fn score_ok() {
+        assert_eq!(score("gh", "github"), Some(1 + 3 + 1 - 2));
+        assert_eq!(score("db", "prod-db"), Some(1 + 3 + 1 + 5));
+        assert_eq!(score("GIT", "github"), Some(1 + 3 + 1 + 5 + 1 + 5));
+        assert_eq!(score("hg", "github"), None);
+        assert!(score("gh", "git hub") > score("gh", "github"));
+    }
+
+    #[test]
+    fn filter_ok() {
+        let items = vec![
+            Item::text("prod-db.example.com"),
+            Item::text("staging.example.com"),
+            Item::text("dev-db.example.com"),
+        ];
+        let actual = filter(items, "db");
+        assert_eq!(
+            titles(&actual),
+            vec!["prod-db.example.com", "dev-db.example.com"]
+        );
+    }
+
+    #[test]
+    fn filter_words_ok() {
+        let items = vec![
+            Item::text("prod-db.example.com"),
+            Item::text("dev-db.example.com"),
+            Item::text("dev-web.example.com"),
+        ];
+        let actual = filter(items, "db dev");
+        assert_eq!(
+            titles(&actual),
+            vec!["dev-db.example.com", "dev-web.example.com"]
+        );
+    }
+
+    #[test]
+    fn filter_empty_query_ok() {
+        let items = vec![Item::text("b"), Item::text("a")];
+        let actual = filter(items, "  ");
+        assert_eq!(titles(&actual), vec!["b", "a"]);
+    }
+
+    #[test]
+    fn filter_rank_ok() {
+        let items = vec![Item::text("xaxbxc"), Item::text("abc")];
+        let actual = filter(items, "abc");
+        assert_eq!(titles(&actual), vec!["abc", "xaxbxc"]);
+    }
+}
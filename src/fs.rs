@@ -1,6 +1,6 @@
 use std::fs::{self, File};
 use std::io::{BufReader, BufWriter, Read, Write};
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
 use crate::error::Result;
 
@@ -24,6 +24,21 @@ pub fn write_file<P: AsRef<Path>>(path: P, contents: &[u8]) -> Result<()> {
     Ok(())
 }
 
+// Keep the first original only, so repeated installs never overwrite it with an edited config
+pub fn backup_file<P: AsRef<Path>>(path: P) -> Result<Option<PathBuf>> {
+    let path = path.as_ref();
+    if !path.exists() {
+        return Ok(None);
+    }
+    let mut name = path.as_os_str().to_owned();
+    name.push(".cargo-launcher.bak");
+    let backup = PathBuf::from(name);
+    if !backup.exists() {
+        fs::copy(path, &backup)?;
+    }
+    Ok(Some(backup))
+}
+
 #[cfg(test)]
 mod tests {
 
@@ -80,4 +95,25 @@ mod tests {
         let actual = write_file(&tmp_file, b"write");
         assert!(actual.is_ok());
     }
+
+    #[test]
+    fn backup_file_keep_original_ok() {
+        let tmp_dir = TempDir::new("").unwrap();
+        let tmp_file = tmp_dir.path().join("config.toml");
+        write_file(&tmp_file, b"original").unwrap();
+
+        let backup = backup_file(&tmp_file).unwrap().unwrap();
+        write_file(&tmp_file, b"edited").unwrap();
+        backup_file(&tmp_file).unwrap();
+
+        assert_eq!(read_file(&backup).unwrap(), b"original");
+    }
+
+    #[test]
+    fn backup_file_not_file_ok() {
+        let tmp_dir = TempDir::new("").unwrap();
+        let tmp_file = tmp_dir.path().join("not_found.toml");
+        let actual = backup_file(&tmp_file).unwrap();
+        assert!(actual.is_none());
+    }
 }
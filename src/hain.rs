@@ -1,4 +1,3 @@
-use dirs;
 use failure::*;
 use log::*;
 use std::fs;
@@ -6,8 +5,9 @@ use std::path::PathBuf;
 
 use crate::cargo::CargoConfig;
 use crate::error::Result;
-use crate::fs::write_file;
+use crate::fs::{mk_dir, write_file};
 use crate::launcher::{LauncherConfig, LauncherLike};
+use crate::subcommand::Subcommand;
 use crate::tpl::{self, Param};
 
 const INDEX_JS_BIN: &[u8] = include_bytes!("asset/hain/index.js");
@@ -27,29 +27,27 @@ impl<'a> Hain<'a> {
     }
 
     #[cfg(target_os = "macos")]
-    fn application_config(&self) -> Result<PathBuf> {
+    fn plugins_dir(&self) -> Result<PathBuf> {
         let mut path = dirs::home_dir().ok_or_else(|| err_msg("Notfound home dir"))?;
         path.push("Library");
         path.push("Application Support");
         path.push("hain-user");
         path.push("devplugins");
-        path.push(self.plugin_name());
         Ok(path)
     }
 
     #[cfg(target_os = "linux")]
-    fn application_config(&self) -> Result<PathBuf> {
+    fn plugins_dir(&self) -> Result<PathBuf> {
         let mut path = dirs::config_dir().ok_or_else(|| err_msg("Notfound home dir"))?;
         if let Some(home) = std::env::var_os("XDG_CONFIG_HOME") {
             path = PathBuf::from(home);
         };
         path.push("hain-user/devplugins");
-        path.push(self.plugin_name());
         Ok(path)
     }
 
     #[cfg(target_os = "windows")]
-    fn application_config(&self) -> Result<PathBuf> {
+    fn plugins_dir(&self) -> Result<PathBuf> {
         let local = std::env::var_os("LOCALAPPDATA");
         let user = std::env::var_os("USERPROFILE");
 
@@ -62,55 +60,56 @@ impl<'a> Hain<'a> {
             _ => bail!("Notfound home dir"),
         };
         path.push("hain-user/devplugins");
-        path.push(self.plugin_name());
         Ok(path)
     }
 
-    fn index_js_path(&self) -> PathBuf {
-        let mut buf = self.launcher_config.work_dir.clone();
-        buf.push("index.js");
-        buf
+    // Hain plugin has only one prefix, so one plugin is generated per keyword
+    fn plugin_name(&self, command: &Subcommand) -> String {
+        format!("hain-plugin-{}", command.keyword)
     }
 
-    fn package_json_path(&self) -> PathBuf {
+    fn plugin_dir(&self, command: &Subcommand) -> PathBuf {
         let mut buf = self.launcher_config.work_dir.clone();
-        buf.push("package.json");
+        buf.push(self.plugin_name(command));
         buf
     }
 
-    fn icon_path(&self) -> PathBuf {
-        let mut buf = self.launcher_config.work_dir.clone();
-        buf.push("icon.png");
+    fn plugin_path(&self, command: &Subcommand, file: &str) -> PathBuf {
+        let mut buf = self.plugin_dir(command);
+        buf.push(file);
         buf
     }
 
-    fn index_js(&self) -> Result<String> {
+    fn index_js(&self, command: &Subcommand) -> Result<String> {
         let mut params = Param::new();
-        params.insert("name", self.cargo_config.name());
+        params.insert("shim", &self.launcher_config.shim());
+        params.insert("bin", &self.launcher_config.bin());
+        params.insert("options", &command.run_options(self.cargo_config));
+        params.insert("debounce_ms", &self.cargo_config.debounce_ms());
+        params.insert("input", self.cargo_config.input().as_str());
         let tpl = String::from_utf8_lossy(INDEX_JS_BIN).into_owned();
-        let contents = tpl::render(&tpl, &params)?;
+        let contents = tpl::render_raw(&tpl, &params)?;
 
         Ok(contents)
     }
 
-    fn package_json(&self) -> Result<String> {
+    fn package_json(&self, command: &Subcommand) -> Result<String> {
         let conf = self.cargo_config;
+        let prefix = format!("/{}", command.keyword);
         let mut params = Param::new();
-        params.insert("name", conf.name());
+        params.insert("plugin", &self.plugin_name(command));
         params.insert("version", conf.version());
-        params.insert("description", conf.description());
         params.insert("author", &conf.author());
+        params.insert("prefix", &prefix);
+        params.insert("usage", &format!("type {} {}", prefix, command.description));
+        params.insert("redirect", &format!("{} ", prefix));
 
         let tpl = String::from_utf8_lossy(PACKAGE_JSON_BIN).into_owned();
-        let contents = tpl::render(&tpl, &params)?;
+        let contents = tpl::render_raw(&tpl, &params)?;
 
         Ok(contents)
     }
 
-    fn plugin_name(&self) -> String {
-        format!("hain-plugin-{}", self.cargo_config.name())
-    }
-
     fn icon(&self) -> Result<Vec<u8>> {
         self.launcher_config.icon()
     }
@@ -122,39 +121,91 @@ impl<'a> LauncherLike for Hain<'a> {
     }
 
     fn gen(&self) -> Result<Vec<PathBuf>> {
-        let index = self.index_js_path();
-        write_file(&index, self.index_js()?.as_bytes())?;
+        let mut paths = vec![];
+        for command in &self.launcher_config.commands {
+            mk_dir(self.plugin_dir(command))?;
+
+            let index = self.plugin_path(command, "index.js");
+            write_file(&index, self.index_js(command)?.as_bytes())?;
 
-        let package = self.package_json_path();
-        write_file(&package, self.package_json()?.as_bytes())?;
+            let package = self.plugin_path(command, "package.json");
+            write_file(&package, self.package_json(command)?.as_bytes())?;
 
-        let icon = self.icon_path();
-        write_file(&icon, &self.icon()?[..])?;
+            let icon = self.plugin_path(command, "icon.png");
+            write_file(&icon, &self.icon()?[..])?;
 
-        Ok(vec![index, package, icon])
+            paths.extend(vec![index, package, icon]);
+        }
+        Ok(paths)
     }
 
     fn deploy(&self, paths: Vec<PathBuf>) -> Result<()> {
-        let sink_dir = self.application_config()?;
-        fs::create_dir_all(&sink_dir)?;
+        let base = &self.launcher_config.work_dir;
+        let sink_dir = self.plugins_dir()?;
         for path in paths {
             debug!("path: {:?}", &path);
             debug!("sink: {:?}", &sink_dir);
-            let name = path.file_name().ok_or_else(|| err_msg("Not file type"))?;
-            let mut sink = sink_dir.clone();
-            sink.push(name);
+            let relative = path.strip_prefix(base)?;
+            let sink = sink_dir.join(relative);
+            let parent = sink.parent().ok_or_else(|| err_msg("Not file type"))?;
+            fs::create_dir_all(parent)?;
             fs::copy(&path, sink)?;
         }
         Ok(())
     }
 
     fn completed_message(&self) -> Result<String> {
-        let msg = r#"
+        let dir = self.plugins_dir()?;
+        let paths = self
+            .launcher_config
+            .commands
+            .iter()
+            .map(|command| {
+                format!(
+                    "  {}",
+                    dir.join(self.plugin_name(command)).to_string_lossy()
+                )
+            })
+            .collect::<Vec<String>>()
+            .join("\n");
+        let msg = format!(
+            r#"
 Install completed!!
 Restart of the hain is required.
 
-Installed path: "#;
-        let path = self.application_config()?;
-        Ok(format!("{}{}", msg, path.to_string_lossy()))
+Installed path:
+{}"#,
+            paths
+        );
+        Ok(msg)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use crate::hain::*;
+
+    #[test]
+    fn package_json_prefix_ok() {
+        let cargo_config = CargoConfig::dummy("test-bin");
+        let launcher_config = LauncherConfig::dummy();
+        let hain = Hain::new(&cargo_config, &launcher_config);
+        let command = Subcommand {
+            keyword: String::from("test-bin-encode"),
+            title: String::from("test-bin encode"),
+            description: String::from("Encode \"the\" input"),
+            args: vec![String::from("encode")],
+        };
+        let actual: serde_json::Value =
+            serde_json::from_str(&hain.package_json(&command).unwrap()).unwrap();
+        assert_eq!(actual["name"], "hain-plugin-test-bin-encode");
+        assert_eq!(actual["hain"]["prefix"], "/test-bin-encode");
+        assert_eq!(
+            actual["hain"]["usage"],
+            r#"type /test-bin-encode Encode "the" input"#
+        );
+        let actual = hain.index_js(&command).unwrap();
+        assert!(actual.contains(r#""--prefix-arg","encode"]"#));
     }
 }
@@ -0,0 +1,180 @@
+use failure::*;
+use log::*;
+use std::fs;
+use std::path::PathBuf;
+
+use crate::cache::ERROR_ICON;
+use crate::cargo::CargoConfig;
+use crate::error::Result;
+use crate::fs::{mk_dir, write_file};
+use crate::launcher::{LauncherConfig, LauncherLike};
+use crate::tpl::{self, Param};
+
+const INIT_LUA_BIN: &[u8] = include_bytes!("asset/hammerspoon/init.lua");
+
+pub struct Hammerspoon<'a> {
+    cargo_config: &'a CargoConfig,
+    launcher_config: &'a LauncherConfig<'a>,
+}
+
+impl<'a> Hammerspoon<'a> {
+    pub fn new(
+        cargo_config: &'a CargoConfig,
+        launcher_config: &'a LauncherConfig,
+    ) -> Hammerspoon<'a> {
+        Hammerspoon {
+            cargo_config,
+            launcher_config,
+        }
+    }
+
+    fn application_config(&self) -> Result<PathBuf> {
+        let mut path = dirs::home_dir().ok_or_else(|| err_msg("Notfound home dir"))?;
+        path.push(".hammerspoon/Spoons");
+        path.push(self.spoon_dir_name());
+        Ok(path)
+    }
+
+    fn spoon_name(&self) -> String {
+        self.cargo_config
+            .name()
+            .split(|c: char| !c.is_ascii_alphanumeric())
+            .filter(|word| !word.is_empty())
+            .map(|word| {
+                let mut chars = word.chars();
+                match chars.next() {
+                    Some(first) => first.to_uppercase().chain(chars).collect::<String>(),
+                    None => String::new(),
+                }
+            })
+            .collect()
+    }
+
+    fn spoon_dir_name(&self) -> String {
+        format!("{}.spoon", self.spoon_name())
+    }
+
+    fn spoon_dir(&self) -> PathBuf {
+        let mut buf = self.launcher_config.work_dir.clone();
+        buf.push(self.spoon_dir_name());
+        buf
+    }
+
+    fn init_lua_path(&self) -> PathBuf {
+        let mut buf = self.spoon_dir();
+        buf.push("init.lua");
+        buf
+    }
+
+    fn icon_path(&self) -> PathBuf {
+        let mut buf = self.spoon_dir();
+        buf.push("icon.png");
+        buf
+    }
+
+    // Shown when the shim itself fails, the failures of the binary get the icon from the shim
+    fn error_icon_path(&self) -> PathBuf {
+        let mut buf = self.spoon_dir();
+        buf.push("error.png");
+        buf
+    }
+
+    fn init_lua(&self) -> Result<String> {
+        let conf = self.cargo_config;
+        let mut params = Param::new();
+        params.insert("spoon", &self.spoon_name());
+        params.insert("name", conf.name());
+        params.insert("version", conf.version());
+        params.insert("description", conf.description());
+        params.insert("author", &conf.author());
+        params.insert("shim", &self.launcher_config.shim());
+        params.insert("bin", &self.launcher_config.bin());
+        params.insert("options", &conf.run_options());
+        params.insert("debounce_ms", &conf.debounce_ms());
+        params.insert("input", conf.input().as_str());
+
+        let tpl = String::from_utf8_lossy(INIT_LUA_BIN).into_owned();
+        let contents = tpl::render_raw(&tpl, &params)?;
+
+        Ok(contents)
+    }
+
+    fn icon(&self) -> Result<Vec<u8>> {
+        self.launcher_config.icon()
+    }
+}
+
+impl<'a> LauncherLike for Hammerspoon<'a> {
+    fn before_check(&self) -> Result<()> {
+        Ok(())
+    }
+
+    fn gen(&self) -> Result<Vec<PathBuf>> {
+        mk_dir(self.spoon_dir())?;
+
+        let init = self.init_lua_path();
+        write_file(&init, self.init_lua()?.as_bytes())?;
+
+        let icon = self.icon_path();
+        write_file(&icon, &self.icon()?[..])?;
+
+        let error_icon = self.error_icon_path();
+        write_file(&error_icon, ERROR_ICON)?;
+
+        Ok(vec![init, icon, error_icon])
+    }
+
+    fn deploy(&self, paths: Vec<PathBuf>) -> Result<()> {
+        // Hammerspoon runs only on macOS, the generated spoon is left in the work dir on the others
+        if cfg!(not(target_os = "macos")) {
+            return Ok(());
+        }
+
+        let sink_dir = self.application_config()?;
+        fs::create_dir_all(&sink_dir)?;
+        for path in paths {
+            debug!("path: {:?}", &path);
+            debug!("sink: {:?}", &sink_dir);
+            let name = path.file_name().ok_or_else(|| err_msg("Not file type"))?;
+            let mut sink = sink_dir.clone();
+            sink.push(name);
+            fs::copy(&path, sink)?;
+        }
+        Ok(())
+    }
+
+    fn completed_message(&self) -> Result<String> {
+        let path = if cfg!(target_os = "macos") {
+            self.application_config()?
+        } else {
+            self.spoon_dir()
+        };
+        let msg = format!(
+            r#"
+Install completed!!
+Add the following lines to ~/.hammerspoon/init.lua and reload the config.
+
+  hs.loadSpoon("{}")
+  spoon.{}:bindHotkeys({{ show = {{ {{ "cmd", "alt" }}, "space" }} }})
+
+Spoon path: "#,
+            self.spoon_name(),
+            self.spoon_name()
+        );
+        Ok(format!("{}{}", msg, path.to_string_lossy()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use crate::hammerspoon::*;
+
+    #[test]
+    fn spoon_name_ok() {
+        let cargo_config = CargoConfig::dummy("test-bin");
+        let launcher_config = LauncherConfig::dummy();
+        let hammerspoon = Hammerspoon::new(&cargo_config, &launcher_config);
+        assert_eq!(hammerspoon.spoon_name(), "TestBin");
+    }
+}
@@ -0,0 +1,288 @@
+use failure::*;
+use log::*;
+use serde_derive::*;
+use std::cmp::Reverse;
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::args::RunArgs;
+use crate::error::Result;
+use crate::fs::{mk_dir, read_file, write_file};
+use crate::item::Item;
+use crate::shim;
+
+const MAX_QUERIES: usize = 20;
+const MAX_SELECTIONS: usize = 200;
+// The queries typed within this are the same search, e.g. "h", "he" and "hello" are kept as "hello"
+const TYPING: Duration = Duration::from_secs(30);
+const DAY_SECS: u64 = 24 * 60 * 60;
+
+#[derive(Serialize, Deserialize, Default)]
+struct History {
+    #[serde(default)]
+    queries: Vec<Query>,
+    #[serde(default)]
+    selections: Vec<Selection>,
+}
+
+// The item is told by the title, the arg of the rerun items is changed by the shim
+#[derive(Serialize, Deserialize)]
+struct Selection {
+    title: String,
+    count: u64,
+    time: u64,
+}
+
+impl Selection {
+    // The recent selections weigh more, as the frecency of the browsers
+    fn score(&self, now: u64) -> u64 {
+        let age = now.saturating_sub(self.time);
+        let weight = match age {
+            _ if age < DAY_SECS => 100,
+            _ if age < 7 * DAY_SECS => 70,
+            _ if age < 30 * DAY_SECS => 50,
+            _ if age < 90 * DAY_SECS => 30,
+            _ => 10,
+        };
+        self.count * weight
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct Query {
+    query: String,
+    time: u64,
+}
+
+// The history is kept unlike the cache, e.g. ~/.local/share/cargo-launcher/history/<bin>/<keyword>.json
+fn dir(bin: &str) -> Result<PathBuf> {
+    let mut path = dirs::data_dir().ok_or_else(|| err_msg("Notfound data dir"))?;
+    path.push("cargo-launcher");
+    path.push("history");
+    path.push(file_name(bin)?);
+    Ok(path)
+}
+
+fn path(args: &RunArgs) -> Result<PathBuf> {
+    let bin = shim::bin_name(args);
+    let name = args.keyword.clone().unwrap_or_else(|| bin.clone());
+    let file = format!("{}.json", file_name(&name)?);
+    let mut path = dir(&bin)?;
+    mk_dir(&path)?;
+    path.push(file);
+    Ok(path)
+}
+
+// The names come from the command line and the metadata, ".." or "a/b" would leave the history dir
+fn file_name(name: &str) -> Result<&str> {
+    let mut components = Path::new(name).components();
+    match (components.next(), components.next()) {
+        (Some(Component::Normal(_)), None) => Ok(name),
+        _ => bail!("Invalid name for the history: {:?}", name),
+    }
+}
+
+// The history of every keyword of the binary, the name is the file name of the binary
+pub fn clear(name: &str) -> Result<String> {
+    let path = dir(name)?;
+    if !path.exists() {
+        return Ok(format!("No history of {}", name));
+    }
+    fs::remove_dir_all(&path)?;
+    Ok(format!("Cleared the history of {}", name))
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|now| now.as_secs())
+        .unwrap_or_default()
+}
+
+pub fn record(args: &RunArgs, query: &str) {
+    let result = path(args).and_then(|path| record_to(&path, query, now()));
+    if let Err(err) = result {
+        debug!("history: {}", err);
+    }
+}
+
+// The most recent first, the typing of the same search replaces the previous query
+fn record_to(path: &Path, query: &str, now: u64) -> Result<()> {
+    let query = query.trim();
+    if query.is_empty() {
+        return Ok(());
+    }
+    let mut history = load(path);
+    let typing = history.queries.first().is_some_and(|last| {
+        now.saturating_sub(last.time) < TYPING.as_secs()
+            && (query.starts_with(&last.query) || last.query.starts_with(query))
+    });
+    if typing {
+        history.queries.remove(0);
+    }
+    history.queries.retain(|recent| recent.query != query);
+    history.queries.insert(
+        0,
+        Query {
+            query: query.to_owned(),
+            time: now,
+        },
+    );
+    history.queries.truncate(MAX_QUERIES);
+    save(path, &history)
+}
+
+pub fn select(args: &RunArgs, title: &str) {
+    let result = path(args).and_then(|path| select_to(&path, title, now()));
+    if let Err(err) = result {
+        debug!("history: {}", err);
+    }
+}
+
+fn select_to(path: &Path, title: &str, now: u64) -> Result<()> {
+    let mut history = load(path);
+    let count = match history.selections.iter().position(|s| s.title == title) {
+        Some(pos) => history.selections.remove(pos).count,
+        None => 0,
+    };
+    history.selections.insert(
+        0,
+        Selection {
+            title: title.to_owned(),
+            count: count + 1,
+            time: now,
+        },
+    );
+    history.selections.truncate(MAX_SELECTIONS);
+    save(path, &history)
+}
+
+// The items selected often and recently come first, the others keep the order of the binary
+pub fn rank(args: &RunArgs, items: Vec<Item>) -> Vec<Item> {
+    match path(args) {
+        Ok(path) => rank_by(&load(&path), items, now()),
+        Err(err) => {
+            debug!("history: {}", err);
+            items
+        }
+    }
+}
+
+fn rank_by(history: &History, items: Vec<Item>, now: u64) -> Vec<Item> {
+    let mut scored = items
+        .into_iter()
+        .map(|item| {
+            let score = history
+                .selections
+                .iter()
+                .find(|selection| selection.title == item.title)
+                .map_or(0, |selection| selection.score(now));
+            (score, item)
+        })
+        .collect::<Vec<(u64, Item)>>();
+    scored.sort_by_key(|(score, _)| Reverse(*score));
+    scored.into_iter().map(|(_, item)| item).collect()
+}
+
+pub fn recent(args: &RunArgs) -> Vec<String> {
+    match path(args) {
+        Ok(path) => load(&path)
+            .queries
+            .into_iter()
+            .map(|recent| recent.query)
+            .collect(),
+        Err(err) => {
+            debug!("history: {}", err);
+            vec![]
+        }
+    }
+}
+
+fn load(path: &Path) -> History {
+    read_file(path)
+        .ok()
+        .and_then(|data| serde_json::from_slice(&data).ok())
+        .unwrap_or_default()
+}
+
+// Renamed at once, the shims of the other keystrokes never read a half written file
+fn save(path: &Path, history: &History) -> Result<()> {
+    let tmp = path.with_extension("tmp");
+    write_file(&tmp, serde_json::to_string(history)?.as_bytes())?;
+    fs::rename(&tmp, path)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+
+    use crate::history::*;
+    use tempdir::TempDir;
+
+    fn queries(path: &Path) -> Vec<String> {
+        load(path).queries.into_iter().map(|q| q.query).collect()
+    }
+
+    #[test]
+    fn record_typing_ok() {
+        let tmp_dir = TempDir::new("record_typing_ok").unwrap();
+        let path = tmp_dir.path().join("test.json");
+        for query in &["h", "he", "hello", "hell"] {
+            record_to(&path, query, 100).unwrap();
+        }
+        assert_eq!(queries(&path), vec!["hell"]);
+        record_to(&path, "world", 101).unwrap();
+        // The same search after a while is a new query
+        record_to(&path, "w", 200).unwrap();
+        assert_eq!(queries(&path), vec!["w", "world", "hell"]);
+    }
+
+    #[test]
+    fn record_dedup_ok() {
+        let tmp_dir = TempDir::new("record_dedup_ok").unwrap();
+        let path = tmp_dir.path().join("test.json");
+        record_to(&path, "a", 100).unwrap();
+        record_to(&path, "b", 100).unwrap();
+        record_to(&path, "a", 100).unwrap();
+        record_to(&path, " ", 100).unwrap();
+        assert_eq!(queries(&path), vec!["a", "b"]);
+    }
+
+    #[test]
+    fn rank_ok() {
+        let tmp_dir = TempDir::new("rank_ok").unwrap();
+        let path = tmp_dir.path().join("test.json");
+        let now = 100 * DAY_SECS;
+        select_to(&path, "b", now - 60 * DAY_SECS).unwrap();
+        select_to(&path, "b", now - 60 * DAY_SECS).unwrap();
+        select_to(&path, "c", now).unwrap();
+        let items = vec![Item::text("a"), Item::text("b"), Item::text("c")];
+        let actual = rank_by(&load(&path), items, now)
+            .into_iter()
+            .map(|item| item.title)
+            .collect::<Vec<String>>();
+        // c: 1 * 100, b: 2 * 30
+        assert_eq!(actual, vec!["c", "b", "a"]);
+    }
+
+    #[test]
+    fn clear_notfound_ok() {
+        let actual = clear("cargo-launcher-notfound-bin").unwrap();
+        assert_eq!(actual, "No history of cargo-launcher-notfound-bin");
+    }
+
+    #[test]
+    fn clear_parent_ng() {
+        for name in &["..", "../../..", "a/b", "/", ""] {
+            assert!(clear(name).is_err(), "{}", name);
+        }
+    }
+
+    #[test]
+    fn path_keyword_ng() {
+        let mut args = RunArgs::dummy("test-bin");
+        args.keyword = Some(String::from("../test-bin"));
+        assert!(path(&args).is_err());
+    }
+}
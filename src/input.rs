@@ -0,0 +1,120 @@
+use failure::*;
+use log::*;
+use std::io::{self, Read};
+use std::process::Command;
+use std::time::Duration;
+
+use crate::args::RunArgs;
+use crate::error::Result;
+use crate::shim;
+
+const PASTE_TIMEOUT: Duration = Duration::from_secs(2);
+
+value_enum! {
+    pub enum Input: Args {
+        Args => "args",
+        StdinFromQuery => "stdin-from-query",
+        StdinFromClipboard => "stdin-from-clipboard",
+        StdinFromSelection => "stdin-from-selection",
+    }
+}
+
+// The data written to the stdin of the binary, None leaves the stdin empty
+pub fn read(args: &RunArgs) -> Result<Option<String>> {
+    let input = match args.input {
+        Input::Args => return Ok(None),
+        Input::StdinFromQuery => args.query.join(" "),
+        // The plugins with the clipboard API write it to the stdin of the shim
+        _ if args.stdin => {
+            let mut input = String::new();
+            io::stdin().read_to_string(&mut input)?;
+            input
+        }
+        Input::StdinFromClipboard => paste(&clipboard_commands())?,
+        Input::StdinFromSelection => paste(&selection_commands())?,
+    };
+    Ok(Some(input))
+}
+
+// The first command that works is used, e.g. wl-paste fails outside of Wayland
+fn paste(commands: &[&[&str]]) -> Result<String> {
+    if commands.is_empty() {
+        bail!("The selection is read by the plugin on this platform")
+    }
+    for command in commands {
+        let mut cmd = Command::new(command[0]);
+        cmd.args(&command[1..]);
+        match shim::exec(cmd, Some(PASTE_TIMEOUT)) {
+            Ok(output) if output.success() => return Ok(output.stdout),
+            Ok(output) => debug!("paste: {}: {}", command[0], output.summary()),
+            Err(err) => debug!("paste: {}: {}", command[0], err),
+        }
+    }
+    let names = commands
+        .iter()
+        .map(|command| command[0])
+        .collect::<Vec<&str>>();
+    bail!("Notfound the clipboard command({})", names.join(", "))
+}
+
+fn clipboard_commands() -> Vec<&'static [&'static str]> {
+    if cfg!(target_os = "macos") {
+        vec![&["pbpaste"]]
+    } else if cfg!(target_os = "windows") {
+        vec![&["powershell", "-NoProfile", "-Command", "Get-Clipboard -Raw"]]
+    } else {
+        vec![
+            &["wl-paste", "--no-newline"],
+            &["xclip", "-selection", "clipboard", "-o"],
+            &["xsel", "--clipboard", "--output"],
+        ]
+    }
+}
+
+// Only X11 and Wayland have the selection apart from the clipboard
+fn selection_commands() -> Vec<&'static [&'static str]> {
+    if cfg!(any(target_os = "macos", target_os = "windows")) {
+        vec![]
+    } else {
+        vec![
+            &["wl-paste", "--primary", "--no-newline"],
+            &["xclip", "-selection", "primary", "-o"],
+            &["xsel", "--primary", "--output"],
+        ]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use crate::input::*;
+    use std::str::FromStr;
+
+    #[test]
+    fn from_str_ok() {
+        for variant in &Input::variants() {
+            assert_eq!(Input::from_str(variant).unwrap().as_str(), *variant);
+        }
+        assert_eq!(
+            Input::from_str("Stdin-From-Query").unwrap(),
+            Input::StdinFromQuery
+        );
+        assert!(Input::from_str("stdin").is_err());
+    }
+
+    #[test]
+    fn read_query_ok() {
+        let mut args = RunArgs::dummy("cat");
+        assert_eq!(read(&args).unwrap(), None);
+        args.input = Input::StdinFromQuery;
+        args.query = vec![String::from("a"), String::from("b")];
+        assert_eq!(read(&args).unwrap().as_deref(), Some("a b"));
+    }
+
+    #[test]
+    fn paste_notfound_ng() {
+        let actual = paste(&[&["cargo-launcher-notfound-paste"]]);
+        assert!(actual.is_err());
+        assert!(paste(&[]).is_err());
+    }
+}
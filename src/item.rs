@@ -0,0 +1,135 @@
+use serde_derive::*;
+
+pub use cargo_launcher_server::{Action, ActionKind, Item};
+
+// The title of the action shown by the launchers
+pub fn title(kind: ActionKind) -> &'static str {
+    match kind {
+        ActionKind::Copy => "Added to Clipboard",
+        ActionKind::Paste => "Paste",
+        ActionKind::Url => "Open URL",
+        ActionKind::Open => "Open",
+        ActionKind::Reveal => "Reveal in File Manager",
+        ActionKind::Terminal => "Run in Terminal",
+        ActionKind::Rerun => "Search Again",
+        ActionKind::CopyFile => "Copy File",
+        ActionKind::Submit => "Run",
+    }
+}
+
+#[derive(Deserialize)]
+struct Items {
+    items: Vec<Item>,
+}
+
+// Result protocol({"items": [...]}) or plain text(one item per line)
+pub fn parse(stdout: &str) -> Vec<Item> {
+    let text = stdout.trim();
+    if text.starts_with('{') {
+        if let Ok(parsed) = serde_json::from_str::<Items>(text) {
+            return parsed.items;
+        }
+    }
+    text.lines()
+        .filter(|line| !line.is_empty())
+        .map(Item::text)
+        .collect()
+}
+
+// Stream protocol, each line is plain text or a JSON item, and {"done": true} ends the results
+// before the binary exits
+pub enum Line {
+    Items(Vec<Item>),
+    Done,
+}
+
+#[derive(Deserialize)]
+struct Done {
+    done: bool,
+}
+
+pub fn parse_line(line: &str) -> Line {
+    let text = line.trim();
+    if text.starts_with('{') {
+        if let Ok(parsed) = serde_json::from_str::<Done>(text) {
+            if parsed.done {
+                return Line::Done;
+            }
+        }
+        if let Ok(item) = serde_json::from_str::<Item>(text) {
+            return Line::Items(vec![item]);
+        }
+        if let Ok(parsed) = serde_json::from_str::<Items>(text) {
+            return Line::Items(parsed.items);
+        }
+    }
+    if text.is_empty() {
+        return Line::Items(vec![]);
+    }
+    Line::Items(vec![Item::text(text)])
+}
+
+#[cfg(test)]
+mod tests {
+
+    use crate::item::*;
+
+    #[test]
+    fn parse_plain_text_ok() {
+        let actual = parse("first\n\nsecond\n");
+        assert_eq!(actual, vec![Item::text("first"), Item::text("second")]);
+    }
+
+    #[test]
+    fn parse_protocol_ok() {
+        let stdout = r#"{"items": [{"title": "t", "subtitle": "s", "valid": false, "action": "url", "actions": [{"title": "a", "arg": "b"}, {"title": "c", "arg": "d", "type": "reveal"}]}]}"#;
+        let actual = parse(stdout);
+        assert_eq!(actual.len(), 1);
+        assert_eq!(actual[0].title, "t");
+        assert_eq!(actual[0].subtitle, Some(String::from("s")));
+        assert_eq!(actual[0].arg(), "t");
+        assert!(!actual[0].valid);
+        assert_eq!(actual[0].action(), ActionKind::Url);
+        assert_eq!(actual[0].actions[0].arg, "b");
+        assert_eq!(actual[0].actions[0].kind, ActionKind::Copy);
+        assert_eq!(actual[0].actions[1].kind, ActionKind::Reveal);
+    }
+
+    #[test]
+    fn parse_broken_json_ok() {
+        let actual = parse("{not json}");
+        assert_eq!(actual, vec![Item::text("{not json}")]);
+    }
+
+    #[test]
+    fn parse_empty_ok() {
+        let actual = parse("");
+        assert!(actual.is_empty());
+    }
+
+    fn line_items(line: &str) -> Vec<Item> {
+        match parse_line(line) {
+            Line::Items(items) => items,
+            Line::Done => panic!("unexpected done"),
+        }
+    }
+
+    #[test]
+    fn parse_line_ok() {
+        assert_eq!(line_items(" first \n"), vec![Item::text("first")]);
+        assert!(line_items("").is_empty());
+        let actual = line_items(r#"{"title": "t", "arg": "a"}"#);
+        assert_eq!(actual[0].arg(), "a");
+        let actual = line_items(r#"{"items": [{"title": "a"}, {"title": "b"}]}"#);
+        assert_eq!(actual.len(), 2);
+    }
+
+    #[test]
+    fn parse_line_done_ok() {
+        assert!(match parse_line(r#"{"done": true}"#) {
+            Line::Done => true,
+            Line::Items(_) => false,
+        });
+        assert_eq!(line_items(r#"{"done": false}"#).len(), 1);
+    }
+}
@@ -1,3 +1,5 @@
+use failure::err_msg;
+use log::*;
 use std::path::PathBuf;
 use structopt::clap::*;
 
@@ -6,8 +8,16 @@ use crate::alfred::Alfred;
 use crate::args::Args;
 use crate::cargo::CargoConfig;
 use crate::error::Result;
+use crate::espanso::Espanso;
 use crate::fs::*;
 use crate::hain::Hain;
+use crate::hammerspoon::Hammerspoon;
+use crate::raycast::Raycast;
+use crate::shell::Shell;
+use crate::sherlock::Sherlock;
+use crate::subcommand::{self, Subcommand};
+use crate::walker::Walker;
+use crate::which::which;
 
 const WORK_PATH: &str = "target/launcher";
 const ICON_BIN: &[u8] = include_bytes!("asset/icon.png");
@@ -18,6 +28,12 @@ arg_enum! {
         Alfred,
         Hain,
         Albert,
+        Espanso,
+        Shell,
+        Raycast,
+        Hammerspoon,
+        Walker,
+        Sherlock,
     }
 }
 impl Launcher {
@@ -30,16 +46,33 @@ impl Launcher {
             Launcher::Alfred => Box::new(Alfred::new(cargo_config, launcher_config)),
             Launcher::Hain => Box::new(Hain::new(cargo_config, launcher_config)),
             Launcher::Albert => Box::new(Albert::new(cargo_config, launcher_config)),
+            Launcher::Espanso => Box::new(Espanso::new(cargo_config, launcher_config)),
+            Launcher::Shell => Box::new(Shell::new(cargo_config, launcher_config)),
+            Launcher::Raycast => Box::new(Raycast::new(cargo_config, launcher_config)),
+            Launcher::Hammerspoon => Box::new(Hammerspoon::new(cargo_config, launcher_config)),
+            Launcher::Walker => Box::new(Walker::new(cargo_config, launcher_config)),
+            Launcher::Sherlock => Box::new(Sherlock::new(cargo_config, launcher_config)),
         }
     }
 }
 
 pub struct LauncherConfig<'a> {
     pub work_dir: PathBuf,
+    pub shim_path: PathBuf,
+    pub bin_path: PathBuf,
+    pub commands: Vec<Subcommand>,
     icon_path: Option<&'a PathBuf>,
 }
 
 impl<'a> LauncherConfig<'a> {
+    pub fn shim(&self) -> String {
+        self.shim_path.to_string_lossy().into_owned()
+    }
+
+    pub fn bin(&self) -> String {
+        self.bin_path.to_string_lossy().into_owned()
+    }
+
     pub fn icon(&self) -> Result<Vec<u8>> {
         let r = match self.icon_path {
             Some(path) => read_file(path)?,
@@ -54,6 +87,24 @@ impl<'a> LauncherConfig<'a> {
     }
 }
 
+#[cfg(test)]
+impl<'a> LauncherConfig<'a> {
+    pub fn dummy() -> LauncherConfig<'a> {
+        LauncherConfig {
+            work_dir: PathBuf::from(WORK_PATH),
+            shim_path: PathBuf::from("/usr/local/bin/cargo-launcher"),
+            bin_path: PathBuf::from("/usr/local/bin/test-bin"),
+            commands: vec![Subcommand {
+                keyword: String::from("test-bin"),
+                title: String::from("test-bin"),
+                description: String::from("Test description"),
+                args: vec![],
+            }],
+            icon_path: None,
+        }
+    }
+}
+
 pub trait LauncherLike {
     fn install(&self) -> Result<String> {
         self.before_check()?;
@@ -68,24 +119,325 @@ pub trait LauncherLike {
 }
 
 pub fn launch(args: &Args, cargo_config: &CargoConfig) -> Result<String> {
+    let launcher = args
+        .launcher
+        .as_ref()
+        .ok_or_else(|| err_msg("Launcher is required"))?;
+    let bin_path = bin_path(args, cargo_config)?;
     let launcher_config = LauncherConfig {
         work_dir: PathBuf::from(WORK_PATH),
+        shim_path: std::env::current_exe()?,
+        commands: subcommand::resolve(cargo_config, &bin_path),
+        bin_path,
         icon_path: args.icon_path.as_ref(),
     };
     launcher_config.mk_dir()?;
 
-    let launcher = args.launcher.instance(cargo_config, &launcher_config);
-    Ok(launcher.install()?)
+    let launcher = launcher.instance(cargo_config, &launcher_config);
+    launcher.install()
+}
+
+// Plugins run the binary by the absolute path, they don't depend on the PATH of the launcher
+fn bin_path(args: &Args, cargo_config: &CargoConfig) -> Result<PathBuf> {
+    let path = match &args.bin_path {
+        Some(path) => path.canonicalize().unwrap_or_else(|_| path.clone()),
+        None => which(cargo_config.name())?,
+    };
+    debug!("bin path: {:?}", &path);
+    Ok(path)
 }
 
 #[cfg(test)]
 mod tests {
 
-    use crate::fs::write_file;
+    use crate::fs::{read_file, write_file};
     use crate::launcher::*;
-    use std::fs;
+    use serde_json::{json, Value};
+    use std::fs::{self, File};
+    use std::io::Read;
     use std::path::PathBuf;
     use tempdir::TempDir;
+    use zip::ZipArchive;
+
+    const SHIM: &str = "/usr/local/bin/cargo-launcher";
+    // The quote breaks the plugins that build the command line by the string concatenation
+    const BIN: &str = "/opt/it's/test-bin";
+    // The query typed in the launcher, it's always the last argument of its own
+    const QUERY: &str = "<query>";
+
+    fn read(paths: &[PathBuf], name: &str) -> String {
+        let path = paths
+            .iter()
+            .find(|path| path.to_string_lossy().ends_with(name))
+            .unwrap_or_else(|| panic!("Notfound {} in {:?}", name, paths));
+        String::from_utf8(read_file(path).unwrap()).unwrap()
+    }
+
+    fn line<'a>(contents: &'a str, pattern: &str, excludes: &[&str]) -> &'a str {
+        contents
+            .lines()
+            .map(str::trim)
+            .find(|line| line.contains(pattern) && excludes.iter().all(|e| !line.contains(e)))
+            .unwrap_or_else(|| panic!("Notfound {} in {}", pattern, contents))
+    }
+
+    fn strings(value: &Value) -> Vec<String> {
+        let values = value.as_array().unwrap().iter();
+        values.map(|v| v.as_str().unwrap().to_owned()).collect()
+    }
+
+    // The value of `<name> = <json>` in the JS, Python and Lua templates
+    fn assigned(contents: &str, name: &str) -> Value {
+        let prefix = format!("{} = ", name);
+        let line = contents
+            .lines()
+            .find(|line| line.starts_with(&prefix))
+            .unwrap_or_else(|| panic!("Notfound {} in {}", name, contents));
+        serde_json::from_str(line[prefix.len()..].trim_end_matches(';')).unwrap()
+    }
+
+    // The list literal of JS, Python or Lua, e.g. ['run', ...OPTIONS, BIN, query]
+    fn list(literal: &str, resolve: &dyn Fn(&str) -> Vec<String>) -> Vec<String> {
+        let start = literal.find(['[', '{']).unwrap();
+        let end = literal.rfind([']', '}']).unwrap();
+        literal[start + 1..end]
+            .split(", ")
+            .map(str::trim)
+            .flat_map(|element| match element.chars().next() {
+                Some('\'') | Some('"') => {
+                    let quoted = format!("\"{}\"", &element[1..element.len() - 1]);
+                    vec![serde_json::from_str::<String>(&quoted).unwrap()]
+                }
+                _ => resolve(element),
+            })
+            .collect()
+    }
+
+    // The plist is XML, the script is unescaped before it's split as the shell does
+    fn alfred(paths: &[PathBuf]) -> Vec<String> {
+        let path = paths
+            .iter()
+            .find(|p| p.ends_with("test-bin.alfredworkflow"));
+        let mut archive = ZipArchive::new(File::open(path.unwrap()).unwrap()).unwrap();
+        let mut plist = String::new();
+        let mut file = archive.by_name("info.plist").unwrap();
+        file.read_to_string(&mut plist).unwrap();
+        let plist = plist
+            .replace("&#x2F;", "/")
+            .replace("&#x27;", "'")
+            .replace("&quot;", "\"")
+            .replace("&lt;", "<")
+            .replace("&gt;", ">")
+            .replace("&amp;", "&");
+        let cmd = line(&plist, "cmd=(", &["--submit", "--select", "--stdin"]);
+        let mut argv = shell_words::split(&cmd["cmd=(".len()..cmd.len() - 1]).unwrap();
+        let run = plist
+            .lines()
+            .skip_while(|l| l.trim() != cmd)
+            .nth(1)
+            .unwrap();
+        assert_eq!(run, r#""${cmd[@]}" "$1""#);
+        argv.push(String::from(QUERY));
+        argv
+    }
+
+    // The JS of Hain and Raycast spawns the shim with the args of the search
+    fn js(contents: &str) -> Vec<String> {
+        let args = line(contents, "const args = [", &["--submit", "--select"]);
+        let spawn = contents
+            .lines()
+            .skip_while(|l| l.trim() != args)
+            .nth(1)
+            .unwrap();
+        assert_eq!(spawn.trim(), "const child = spawn(SHIM, args);");
+        let mut argv = strings(&json!([assigned(contents, "const SHIM")]));
+        argv.extend(list(args, &|element| match element {
+            "...OPTIONS" => strings(&assigned(contents, "const OPTIONS")),
+            // The clipboard isn't read by default
+            "...stdin" => vec![],
+            "BIN" | "binPath || BIN" => vec![BIN.to_owned()],
+            "query" | "searchText" => vec![QUERY.to_owned()],
+            _ => panic!("Unknown element {}", element),
+        }));
+        argv
+    }
+
+    fn albert(contents: &str) -> Vec<String> {
+        let args = line(contents, "cmd = [", &[]);
+        list(args, &|element| match element {
+            "shim" | "bin" => strings(&json!([assigned(contents, element)])),
+            r#"*command["options"]"# => strings(&assigned(contents, "commands")[0]["options"]),
+            "string" => vec![QUERY.to_owned()],
+            _ => panic!("Unknown element {}", element),
+        })
+    }
+
+    // The args of the search are built by the inserts, the input is off by default
+    fn hammerspoon(contents: &str) -> Vec<String> {
+        let decode = line(contents, "obj.options = hs.json.decode(", &[]);
+        let options: String =
+            serde_json::from_str(&decode[decode.find('(').unwrap() + 1..decode.len() - 1]).unwrap();
+        let mut argv = strings(&json!([assigned(contents, "obj.shim")]));
+        let search = contents
+            .lines()
+            .skip_while(|l| *l != "function obj:search(query)")
+            .take_while(|l| !l.contains("hs.task.new"))
+            .map(str::trim)
+            .collect::<Vec<&str>>();
+        let mut skip = false;
+        for line in search {
+            match line {
+                _ if skip => skip = line != "end",
+                "if self.inputData then" => skip = true,
+                "for _, option in ipairs(self.options) do" => {
+                    argv.extend(strings(&serde_json::from_str(&options).unwrap()));
+                    skip = true;
+                }
+                _ if line.starts_with("local args = {") => {
+                    argv.extend(list(line, &|element| panic!("Unknown element {}", element)))
+                }
+                _ if line.starts_with("table.insert(args, ") => {
+                    argv.push(match &line["table.insert(args, ".len()..line.len() - 1] {
+                        "self.bin" => BIN.to_owned(),
+                        "query" => QUERY.to_owned(),
+                        literal => serde_json::from_str(literal).unwrap(),
+                    })
+                }
+                _ => (),
+            }
+        }
+        argv
+    }
+
+    // Fish unescapes only the backslash and the quote in the single quotes
+    fn fish_split(command: &str) -> Vec<String> {
+        let mut words = vec![];
+        let mut chars = command.chars();
+        let (mut word, mut quoted, mut started) = (String::new(), false, false);
+        while let Some(c) = chars.next() {
+            match c {
+                '\\' => match chars.next().unwrap() {
+                    next if !quoted || next == '\\' || next == '\'' => word.push(next),
+                    next => word.extend(&[c, next]),
+                },
+                '\'' => (quoted, started) = (!quoted, true),
+                ' ' if !quoted => {
+                    if started || !word.is_empty() {
+                        words.push(word.split_off(0));
+                    }
+                    started = false;
+                }
+                _ => word.push(c),
+            }
+        }
+        assert!(!quoted, "{}", command);
+        if started || !word.is_empty() {
+            words.push(word);
+        }
+        words
+    }
+
+    // The widgets run the command substitution with the input as the last word
+    fn shell(contents: &str, ext: &str) -> Vec<String> {
+        let line = line(contents, "launcher run", &[]);
+        let start = line.find('(').unwrap();
+        let end = line.rfind(')').unwrap();
+        // The input is quoted, or the shell splits it and expands the globs
+        let (command, input) = line[start + 1..end].rsplit_once(' ').unwrap();
+        assert!(input == r#""$args""# || input == r#""$REPLY""#, "{}", input);
+        let mut argv = match ext {
+            "fish" => fish_split(command),
+            _ => shell_words::split(command).unwrap(),
+        };
+        argv.push(String::from(QUERY));
+        argv
+    }
+
+    // Walker runs the source by the shell, the query is written to the stdin by the here-document
+    fn walker(contents: &str) -> Vec<String> {
+        let src = line(contents, "src = ", &[]);
+        let src: String = serde_json::from_str(&src["src = ".len()..]).unwrap();
+        let (command, here_doc) = src.split_at(src.find(" <<").unwrap());
+        assert!(here_doc.ends_with("\n%TERM%\nCARGO_LAUNCHER_EOF"));
+        shell_words::split(command).unwrap()
+    }
+
+    // Sherlock splits the exec-args by the spaces
+    fn sherlock(contents: &str) -> Vec<String> {
+        let entry: Value = serde_json::from_str(contents).unwrap();
+        let mut argv = vec![entry["args"]["exec"].as_str().unwrap().to_owned()];
+        let exec_args = entry["args"]["exec-args"].as_str().unwrap();
+        argv.extend(exec_args.split(' ').map(|arg| match arg {
+            "{keyword}" => QUERY.to_owned(),
+            arg => arg.to_owned(),
+        }));
+        argv
+    }
+
+    fn espanso(contents: &str) -> Vec<String> {
+        let args = line(contents, "args: ", &[]);
+        let args: Value = serde_json::from_str(&args["args: ".len()..]).unwrap();
+        strings(&args)
+            .into_iter()
+            .map(|arg| match arg.as_str() {
+                "{{form1.args}}" => QUERY.to_owned(),
+                _ => arg,
+            })
+            .collect()
+    }
+
+    fn argvs(launcher: &Launcher, paths: &[PathBuf]) -> Vec<Vec<String>> {
+        match launcher {
+            Launcher::Alfred => vec![alfred(paths)],
+            Launcher::Hain => vec![js(&read(paths, "index.js"))],
+            Launcher::Albert => vec![albert(&read(paths, "__init__.py"))],
+            Launcher::Espanso => vec![espanso(&read(paths, "package.yml"))],
+            Launcher::Shell => ["bash", "zsh", "fish"]
+                .iter()
+                .map(|ext| shell(&read(paths, &format!("test-bin.{}", ext)), ext))
+                .collect(),
+            Launcher::Raycast => vec![js(&read(paths, "test-bin.tsx"))],
+            Launcher::Hammerspoon => vec![hammerspoon(&read(paths, "init.lua"))],
+            Launcher::Walker => vec![walker(&read(paths, "test-bin.walker.toml"))],
+            Launcher::Sherlock => vec![sherlock(&read(paths, "test-bin.sherlock.json"))],
+        }
+    }
+
+    #[test]
+    fn generated_argv_ok() {
+        let cargo_config = CargoConfig::dummy("test-bin");
+        let keyword = ["--keyword", "test-bin"];
+        for variant in &Launcher::variants() {
+            let launcher = variant.parse::<Launcher>().unwrap();
+            let name = variant.to_lowercase();
+            let tmp_dir = TempDir::new("generated_argv_ok").unwrap();
+            let launcher_config = LauncherConfig {
+                work_dir: tmp_dir.path().to_owned(),
+                shim_path: PathBuf::from(SHIM),
+                bin_path: PathBuf::from(BIN),
+                ..LauncherConfig::dummy()
+            };
+            let paths = launcher
+                .instance(&cargo_config, &launcher_config)
+                .gen()
+                .unwrap();
+            // The plugins of the keywords pass the keyword, the others have one command
+            let keyword: &[&str] = match launcher {
+                Launcher::Alfred | Launcher::Hain | Launcher::Albert => &keyword,
+                _ => &[],
+            };
+            let mut expected = vec![SHIM, "launcher", "run", "--launcher", &name];
+            expected.extend(&["--tokenize", "whitespace"]);
+            expected.extend(keyword);
+            match launcher {
+                Launcher::Walker => expected.extend(&["--query-stdin", "--", BIN]),
+                _ => expected.extend(&["--", BIN, QUERY]),
+            }
+            for actual in argvs(&launcher, &paths) {
+                assert_eq!(actual, expected, "{}", variant);
+            }
+        }
+    }
 
     #[test]
     fn mk_dir_bore_ok() {
@@ -93,6 +445,9 @@ mod tests {
         let dir = tmp_dir.path().join("work_dir");
         let conf = LauncherConfig {
             work_dir: dir.clone(),
+            shim_path: PathBuf::from("cargo-launcher"),
+            bin_path: PathBuf::from("test-bin"),
+            commands: vec![],
             icon_path: None,
         };
 
@@ -107,6 +462,9 @@ mod tests {
         let dir = tmp_dir.path().join("work_dir");
         let conf = LauncherConfig {
             work_dir: dir.clone(),
+            shim_path: PathBuf::from("cargo-launcher"),
+            bin_path: PathBuf::from("test-bin"),
+            commands: vec![],
             icon_path: None,
         };
 
@@ -124,6 +482,9 @@ mod tests {
         write_file(path, vec![1u8].as_slice()).unwrap();
         let conf = LauncherConfig {
             work_dir: dir.clone(),
+            shim_path: PathBuf::from("cargo-launcher"),
+            bin_path: PathBuf::from("test-bin"),
+            commands: vec![],
             icon_path: None,
         };
 
@@ -139,11 +500,13 @@ mod tests {
         let path = PathBuf::from("notfound-icon-path");
         let conf = LauncherConfig {
             work_dir: dir.clone(),
+            shim_path: PathBuf::from("cargo-launcher"),
+            bin_path: PathBuf::from("test-bin"),
+            commands: vec![],
             icon_path: Some(&path),
         };
 
         let r = conf.icon();
         assert!(r.is_err());
     }
-
 }
@@ -0,0 +1,60 @@
+// Generates an enum of the option values, with the default and the lowercase names used by
+// the flags, the Cargo.toml keys and the serialized items
+//
+//   value_enum! {
+//       pub enum Tokenize: Whitespace {
+//           Whole => "whole",
+//           Whitespace => "whitespace",
+//       }
+//   }
+macro_rules! value_enum {
+    (@count) => { 0 };
+    (@count $head:ident $($tail:ident)*) => { 1 + value_enum!(@count $($tail)*) };
+    (
+        $(#[$meta:meta])*
+        pub enum $name:ident: $default:ident {
+            $($variant:ident => $value:literal,)+
+        }
+    ) => {
+        $(#[$meta])*
+        #[derive(Debug, Clone, Copy, PartialEq, serde_derive::Serialize, serde_derive::Deserialize)]
+        pub enum $name {
+            $(#[serde(rename = $value)] $variant,)+
+        }
+
+        impl Default for $name {
+            fn default() -> Self {
+                $name::$default
+            }
+        }
+
+        impl $name {
+            pub fn variants() -> [&'static str; value_enum!(@count $($variant)+)] {
+                [$($value,)+]
+            }
+
+            pub fn as_str(self) -> &'static str {
+                match self {
+                    $($name::$variant => $value,)+
+                }
+            }
+        }
+
+        impl std::str::FromStr for $name {
+            type Err = String;
+
+            fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+                match s.to_lowercase().as_str() {
+                    $($value => Ok($name::$variant),)+
+                    _ => Err(format!("valid values: {}", $name::variants().join(", "))),
+                }
+            }
+        }
+
+        impl std::fmt::Display for $name {
+            fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+                f.write_str(self.as_str())
+            }
+        }
+    };
+}
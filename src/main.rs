@@ -1,19 +1,43 @@
+#[macro_use]
+mod macros;
+
 mod albert;
 mod alfred;
 mod args;
+mod block;
+mod cache;
 mod cargo;
+mod complete;
 mod core;
 mod error;
+mod espanso;
+mod filter;
 mod fs;
 mod hain;
+mod hammerspoon;
+mod history;
+mod input;
+mod item;
 mod launcher;
+mod mime;
+mod notify;
+mod preview;
+mod raycast;
+mod server;
+mod shell;
+mod sherlock;
+mod shim;
+mod stream;
+mod subcommand;
 mod tpl;
+mod usage;
+mod walker;
+mod which;
 
 use log::debug;
-use pretty_env_logger;
 use std::process::exit;
 
-use crate::args::args;
+use crate::args::{args, Args, HistorySub, Sub};
 use crate::cargo::config;
 use crate::error::Result;
 use crate::launcher::launch;
@@ -23,12 +47,16 @@ const FAILED_CODE: i32 = 1;
 
 fn main() -> Result<()> {
     pretty_env_logger::init();
-    let args = args();
+    let mut args = args();
     debug!("args: {:?}", args);
-    let name = args.bin_name.as_ref().map(String::as_str);
-    let config = config(&None, name)?;
 
-    match launch(&args, &config) {
+    let result = match args.sub {
+        Some(Sub::Run(ref mut run_args)) => shim::run(run_args),
+        Some(Sub::History(HistorySub::Clear { ref name })) => history::clear(name),
+        None => install(&args),
+    };
+
+    match result {
         Ok(msg) => {
             println!("{}", msg);
             exit(SUCCESS_CODE)
@@ -39,3 +67,9 @@ fn main() -> Result<()> {
         }
     }
 }
+
+fn install(args: &Args) -> Result<String> {
+    let name = args.bin_name.as_deref();
+    let config = config(&None, name)?;
+    launch(args, &config)
+}
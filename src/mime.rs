@@ -0,0 +1,183 @@
+use log::*;
+use std::collections::hash_map::DefaultHasher;
+use std::fs;
+use std::hash::Hasher;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use crate::args::RunArgs;
+use crate::cache;
+use crate::error::Result;
+use crate::fs::write_file;
+use crate::item::{self, Action, ActionKind, Item};
+use crate::shim;
+
+// The files of the previous queries are removed after this, the cached results may point them
+const STALE: Duration = Duration::from_secs(24 * 60 * 60);
+const OCTET_STREAM: Mime = mime("application/octet-stream", "bin", "Binary data");
+// Magic numbers at the start of the data, RIFF is checked with the format at 8
+const MAGICS: [(&[u8], Mime); 10] = [
+    (b"\x89PNG\r\n\x1a\n", mime("image/png", "png", "PNG image")),
+    (b"\xff\xd8\xff", mime("image/jpeg", "jpg", "JPEG image")),
+    (b"GIF87a", mime("image/gif", "gif", "GIF image")),
+    (b"GIF89a", mime("image/gif", "gif", "GIF image")),
+    (b"BM", mime("image/bmp", "bmp", "BMP image")),
+    (b"II*\x00", mime("image/tiff", "tiff", "TIFF image")),
+    (b"MM\x00*", mime("image/tiff", "tiff", "TIFF image")),
+    (b"%PDF-", mime("application/pdf", "pdf", "PDF document")),
+    (b"PK\x03\x04", mime("application/zip", "zip", "ZIP archive")),
+    (b"\x1f\x8b", mime("application/gzip", "gz", "Gzip archive")),
+];
+const WEBP: Mime = mime("image/webp", "webp", "WebP image");
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Mime {
+    pub name: &'static str,
+    pub ext: &'static str,
+    pub label: &'static str,
+}
+
+const fn mime(name: &'static str, ext: &'static str, label: &'static str) -> Mime {
+    Mime { name, ext, label }
+}
+
+impl Mime {
+    pub fn is_image(self) -> bool {
+        self.name.starts_with("image/")
+    }
+}
+
+// None is the text, the other data has no valid UTF-8 or has NUL
+pub fn sniff(data: &[u8]) -> Option<Mime> {
+    if let Some((_, mime)) = MAGICS.iter().find(|(magic, _)| data.starts_with(magic)) {
+        return Some(*mime);
+    }
+    if data.len() >= 12 && &data[..4] == b"RIFF" && &data[8..12] == b"WEBP" {
+        return Some(WEBP);
+    }
+    match std::str::from_utf8(data) {
+        Ok(text) if !text.contains('\0') => None,
+        _ => Some(OCTET_STREAM),
+    }
+}
+
+// The output is written to a file, the launchers open, copy or reveal it
+pub fn items(args: &RunArgs, data: &[u8]) -> Vec<Item> {
+    let mime = sniff(data).unwrap_or(OCTET_STREAM);
+    match write(data, mime) {
+        Ok(path) => vec![file_item(args, &path, mime, data.len())],
+        Err(err) => vec![shim::failed_item(args, None, &err.to_string())],
+    }
+}
+
+fn file_item(args: &RunArgs, path: &Path, mime: Mime, size: usize) -> Item {
+    let path = path.to_string_lossy().into_owned();
+    Item {
+        subtitle: Some(format!("{}, {}", mime.name, human_size(size))),
+        arg: Some(path.clone()),
+        // The image is the thumbnail of itself
+        icon: Some(path.clone()).filter(|_| mime.is_image()),
+        action: Some(ActionKind::Open),
+        actions: vec![
+            Action {
+                title: String::from(item::title(ActionKind::CopyFile)),
+                arg: path.clone(),
+                kind: ActionKind::CopyFile,
+            },
+            Action {
+                title: String::from(item::title(ActionKind::Reveal)),
+                arg: path,
+                kind: ActionKind::Reveal,
+            },
+        ],
+        ..Item::text(&format!("{} from {}", mime.label, shim::bin_name(args)))
+    }
+}
+
+// The same output is the same file, e.g. cached or run again
+fn write(data: &[u8], mime: Mime) -> Result<PathBuf> {
+    let dir = cache::dir("output")?;
+    sweep(&dir);
+    let mut hasher = DefaultHasher::new();
+    hasher.write(data);
+    let path = dir.join(format!("{}.{}", hasher.finish(), mime.ext));
+    if !path.exists() {
+        write_file(&path, data)?;
+    }
+    Ok(path)
+}
+
+fn sweep(dir: &Path) {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(err) => {
+            debug!("output: {}", err);
+            return;
+        }
+    };
+    for path in entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+    {
+        let stale = fs::metadata(&path)
+            .and_then(|m| m.modified())
+            .ok()
+            .and_then(|modified| modified.elapsed().ok())
+            .is_some_and(|elapsed| elapsed > STALE);
+        if stale {
+            let _ = fs::remove_file(&path);
+        }
+    }
+}
+
+fn human_size(size: usize) -> String {
+    match size {
+        _ if size < 1024 => format!("{} B", size),
+        _ if size < 1024 * 1024 => format!("{:.1} KB", size as f64 / 1024.0),
+        _ => format!("{:.1} MB", size as f64 / (1024.0 * 1024.0)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use crate::mime::*;
+
+    #[test]
+    fn sniff_ok() {
+        assert_eq!(
+            sniff(b"\x89PNG\r\n\x1a\n\x00\x00").map(|m| m.ext),
+            Some("png")
+        );
+        assert_eq!(
+            sniff(b"RIFF\x00\x00\x00\x00WEBPVP8 ").map(|m| m.ext),
+            Some("webp")
+        );
+        assert_eq!(sniff(b"%PDF-1.7\n").map(|m| m.ext), Some("pdf"));
+        assert_eq!(sniff(b"\xff\xfe\x00a").map(|m| m.ext), Some("bin"));
+        assert_eq!(sniff(b"a\x00b").map(|m| m.ext), Some("bin"));
+        assert_eq!(sniff("text ✓\n".as_bytes()), None);
+        assert_eq!(sniff(b""), None);
+    }
+
+    #[test]
+    fn items_ok() {
+        let args = RunArgs::dummy("test-bin");
+        let data = b"\x89PNG\r\n\x1a\ntest";
+        let actual = items(&args, data);
+        assert_eq!(actual[0].title, "PNG image from test-bin");
+        assert_eq!(actual[0].subtitle.as_deref(), Some("image/png, 12 B"));
+        assert_eq!(actual[0].icon, actual[0].arg);
+        assert_eq!(actual[0].actions[0].kind, ActionKind::CopyFile);
+        let path = actual[0].arg.clone().unwrap();
+        assert!(path.ends_with(".png"));
+        assert_eq!(fs::read(path).unwrap(), data.to_vec());
+    }
+
+    #[test]
+    fn human_size_ok() {
+        assert_eq!(human_size(10), "10 B");
+        assert_eq!(human_size(1536), "1.5 KB");
+        assert_eq!(human_size(3 * 1024 * 1024), "3.0 MB");
+    }
+}
@@ -0,0 +1,206 @@
+use log::*;
+use serde_derive::*;
+use serde_json::json;
+use std::process::Command;
+use std::time::Duration;
+
+use crate::args::RunArgs;
+use crate::error::Result;
+use crate::item::{ActionKind, Item};
+use crate::launcher::Launcher;
+use crate::shim;
+
+// The notification shows a few lines, the rest is cut
+const MAX_LINES: usize = 5;
+const SEND_TIMEOUT: Duration = Duration::from_secs(3);
+
+// Where the output of the binary goes: the list of the launcher, or a notification
+value_enum! {
+    pub enum OutputMode: List {
+        List => "list",
+        Notify => "notify",
+    }
+}
+
+#[derive(Serialize, Debug, PartialEq)]
+pub struct Notification {
+    pub title: String,
+    pub message: String,
+    pub success: bool,
+}
+
+// Shown on every keystroke instead of the results, the binary runs when it's actioned
+pub fn prompt(args: &RunArgs, query: &str) -> Item {
+    let name = name(args);
+    let title = match query.trim() {
+        "" => format!("Run {}", name),
+        query => format!("Run {} {}", name, query),
+    };
+    Item {
+        subtitle: Some(String::from("The result is shown as a notification")),
+        arg: Some(query.to_owned()),
+        action: Some(ActionKind::Submit),
+        ..Item::text(&title)
+    }
+}
+
+// The launchers with the notification API show it, the shim sends it for the others
+pub fn submit(args: &RunArgs, cmd: Command, input: Option<&str>) -> Result<String> {
+    let line = shim::command_line(&cmd, input);
+    let items = match shim::exec_input(cmd, args.timeout_ms.map(Duration::from_millis), input) {
+        Ok(output) => {
+            if !output.success() {
+                shim::log_failure(args, &line, &output.summary(), &output.stderr);
+            }
+            shim::items(args, &output)
+        }
+        Err(err) => {
+            shim::log_failure(args, &line, "spawn failed", &err.to_string());
+            vec![shim::failed_item(args, None, &err.to_string())]
+        }
+    };
+    let notification = notification(args, &items);
+    let value = match args.launcher {
+        // The script prints the message as the arg of the notification output
+        Launcher::Alfred => json!({
+            "alfredworkflow": {
+                "arg": notification.message,
+                "variables": { "notify_title": notification.title },
+            }
+        }),
+        Launcher::Hain | Launcher::Raycast | Launcher::Hammerspoon => json!(notification),
+        Launcher::Albert
+        | Launcher::Walker
+        | Launcher::Sherlock
+        | Launcher::Shell
+        | Launcher::Espanso => {
+            send(&notification);
+            return Ok(String::new());
+        }
+    };
+    Ok(serde_json::to_string(&value)?)
+}
+
+// The failed item is the failure, the titles of the items are the message
+fn notification(args: &RunArgs, items: &[Item]) -> Notification {
+    let name = name(args);
+    if let Some(failed) = items.iter().find(|item| !item.valid) {
+        let message = failed
+            .subtitle
+            .clone()
+            .unwrap_or_else(|| failed.title.clone());
+        return Notification {
+            title: format!("{} failed", name),
+            message,
+            success: false,
+        };
+    }
+    let mut lines = items
+        .iter()
+        .take(MAX_LINES)
+        .map(|item| item.title.as_str())
+        .collect::<Vec<&str>>();
+    if items.len() > MAX_LINES {
+        lines.push("...");
+    }
+    let message = match lines.join("\n") {
+        message if message.is_empty() => String::from("Done"),
+        message => message,
+    };
+    Notification {
+        title: name,
+        message,
+        success: true,
+    }
+}
+
+fn name(args: &RunArgs) -> String {
+    args.keyword.clone().unwrap_or_else(|| shim::bin_name(args))
+}
+
+// The failure is urgent, it stays until it's closed on the most desktops
+fn send(notification: &Notification) {
+    let mut cmd = if cfg!(target_os = "macos") {
+        let sound = if notification.success {
+            ""
+        } else {
+            " sound name \"Basso\""
+        };
+        let script = format!(
+            "on run argv\ndisplay notification (item 2 of argv) with title (item 1 of argv){}\nend run",
+            sound
+        );
+        let mut cmd = Command::new("osascript");
+        cmd.args(["-e", &script]);
+        cmd
+    } else {
+        let (urgency, icon) = if notification.success {
+            ("normal", "dialog-information")
+        } else {
+            ("critical", "dialog-error")
+        };
+        let mut cmd = Command::new("notify-send");
+        cmd.args(["-u", urgency, "-i", icon, "--"]);
+        cmd
+    };
+    cmd.args([&notification.title, &notification.message]);
+    match shim::exec(cmd, Some(SEND_TIMEOUT)) {
+        Ok(output) if output.success() => (),
+        Ok(output) => debug!("notify: {}", output.summary()),
+        Err(err) => debug!("notify: {}", err),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use crate::notify::*;
+
+    #[test]
+    fn prompt_ok() {
+        let mut args = RunArgs::dummy("vpn");
+        let actual = prompt(&args, "");
+        assert_eq!(actual.title, "Run vpn");
+        args.keyword = Some(String::from("vpn-toggle"));
+        let actual = prompt(&args, "on ");
+        assert_eq!(actual.title, "Run vpn-toggle on");
+        assert_eq!(actual.arg.as_deref(), Some("on "));
+        assert_eq!(actual.action, Some(ActionKind::Submit));
+    }
+
+    #[test]
+    fn notification_ok() {
+        let args = RunArgs::dummy("timer");
+        let items = (1..=6)
+            .map(|i| Item::text(&i.to_string()))
+            .collect::<Vec<Item>>();
+        let actual = notification(&args, &items);
+        assert_eq!(actual.title, "timer");
+        assert_eq!(actual.message, "1\n2\n3\n4\n5\n...");
+        assert!(actual.success);
+        assert_eq!(notification(&args, &[]).message, "Done");
+    }
+
+    #[test]
+    fn notification_failed_ok() {
+        let args = RunArgs::dummy("timer");
+        let items = vec![shim::failed_item(&args, Some(2), "error: busy\n")];
+        let actual = notification(&args, &items);
+        assert_eq!(actual.title, "timer failed");
+        assert_eq!(actual.message, "Exit code 2: error: busy");
+        assert!(!actual.success);
+    }
+
+    #[test]
+    #[cfg(not(target_os = "windows"))]
+    fn submit_ok() {
+        let mut args = RunArgs::dummy("echo");
+        args.launcher = Launcher::Raycast;
+        let mut cmd = Command::new("echo");
+        cmd.arg("started");
+        let actual: serde_json::Value =
+            serde_json::from_str(&submit(&args, cmd, None).unwrap()).unwrap();
+        assert_eq!(actual["message"], "started");
+        assert_eq!(actual["success"], true);
+    }
+}
@@ -0,0 +1,111 @@
+use log::*;
+use pulldown_cmark::{html, Options, Parser};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+use crate::cache;
+use crate::core::hash;
+use crate::error::Result;
+use crate::fs::write_file;
+
+const STYLE: &str = "body{font-family:-apple-system,'Segoe UI',sans-serif;margin:12px;}\
+pre,code{font-family:Menlo,Consolas,monospace;white-space:pre-wrap;word-break:break-all;}";
+// The previews of the items no longer shown are removed after this
+const PREVIEW_TTL: Duration = Duration::from_secs(60 * 60);
+
+value_enum! {
+    pub enum PreviewFormat: Text {
+        Text => "text",
+        Markdown => "markdown",
+    }
+}
+
+pub fn html(text: &str, format: PreviewFormat) -> String {
+    let body = match format {
+        PreviewFormat::Text => format!("<pre>{}</pre>", escape_html(text)),
+        PreviewFormat::Markdown => {
+            let mut body = String::new();
+            html::push_html(&mut body, Parser::new_ext(text, Options::all()));
+            body
+        }
+    };
+    format!(
+        "<html><head><meta charset=\"utf-8\"><style>{}</style></head><body>{}</body></html>",
+        STYLE, body
+    )
+}
+
+// Quick Look needs a file, it's named by the hash so the same preview is shared. It's written
+// every time to keep the previews shown fresh, the others are swept when a new one is added
+pub fn write(html: &str) -> Result<PathBuf> {
+    let dir = cache::dir("preview")?;
+    let path = dir.join(format!("{}.html", hash(html)));
+    let added = !path.exists();
+    write_file(&path, html.as_bytes())?;
+    if added {
+        sweep(&dir, PREVIEW_TTL)?;
+    }
+    Ok(path)
+}
+
+fn sweep(dir: &Path, ttl: Duration) -> Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        let stale = fs::metadata(&path)
+            .and_then(|metadata| metadata.modified())
+            .ok()
+            .and_then(|modified| SystemTime::now().duration_since(modified).ok())
+            .is_some_and(|age| age > ttl);
+        if stale {
+            debug!("evict: {:?}", path);
+            // Another shim may have removed it already
+            match fs::remove_file(&path) {
+                Err(err) if err.kind() != io::ErrorKind::NotFound => return Err(err.into()),
+                _ => (),
+            }
+        }
+    }
+    Ok(())
+}
+
+pub fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+
+    use crate::preview::*;
+    use std::thread;
+    use tempdir::TempDir;
+
+    #[test]
+    fn html_text_ok() {
+        let actual = html("a <b>\n  c", PreviewFormat::Text);
+        assert!(actual.contains("<pre>a &lt;b&gt;\n  c</pre>"));
+    }
+
+    #[test]
+    fn html_markdown_ok() {
+        let actual = html("# Title\n\n`code`", PreviewFormat::Markdown);
+        assert!(actual.contains("<h1>Title</h1>"));
+        assert!(actual.contains("<code>code</code>"));
+    }
+
+    #[test]
+    fn sweep_ok() {
+        let tmp_dir = TempDir::new("sweep_ok").unwrap();
+        let dir = tmp_dir.path();
+        write_file(dir.join("old.html"), b"a").unwrap();
+        thread::sleep(Duration::from_millis(500));
+        write_file(dir.join("new.html"), b"b").unwrap();
+        sweep(dir, Duration::from_millis(250)).unwrap();
+        assert!(!dir.join("old.html").exists());
+        assert!(dir.join("new.html").exists());
+    }
+}
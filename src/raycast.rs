@@ -0,0 +1,160 @@
+use failure::*;
+use log::*;
+use std::fs;
+use std::path::PathBuf;
+
+use crate::cargo::CargoConfig;
+use crate::error::Result;
+use crate::fs::{mk_dir, write_file};
+use crate::launcher::{LauncherConfig, LauncherLike};
+use crate::tpl::{self, Param};
+
+const PACKAGE_JSON_BIN: &[u8] = include_bytes!("asset/raycast/package.json");
+const TSCONFIG_JSON_BIN: &[u8] = include_bytes!("asset/raycast/tsconfig.json");
+const COMMAND_TSX_BIN: &[u8] = include_bytes!("asset/raycast/command.tsx");
+
+pub struct Raycast<'a> {
+    cargo_config: &'a CargoConfig,
+    launcher_config: &'a LauncherConfig<'a>,
+}
+
+impl<'a> Raycast<'a> {
+    pub fn new(cargo_config: &'a CargoConfig, launcher_config: &'a LauncherConfig) -> Raycast<'a> {
+        Raycast {
+            cargo_config,
+            launcher_config,
+        }
+    }
+
+    fn application_config(&self) -> Result<PathBuf> {
+        let mut path = dirs::data_dir().ok_or_else(|| err_msg("Notfound data dir"))?;
+        path.push("cargo-launcher/raycast");
+        path.push(self.cargo_config.name());
+        Ok(path)
+    }
+
+    fn extension_dir(&self) -> PathBuf {
+        let mut buf = self.launcher_config.work_dir.clone();
+        buf.push("raycast");
+        buf
+    }
+
+    fn extension_path(&self, relative: &str) -> PathBuf {
+        let mut buf = self.extension_dir();
+        buf.push(relative);
+        buf
+    }
+
+    fn package_json(&self) -> Result<String> {
+        let conf = self.cargo_config;
+        let mut params = Param::new();
+        params.insert("name", conf.name());
+        params.insert("version", conf.version());
+        params.insert("description", conf.description());
+        params.insert("author", &self.author());
+        params.insert("bin", &self.launcher_config.bin());
+
+        let tpl = String::from_utf8_lossy(PACKAGE_JSON_BIN).into_owned();
+        let contents = tpl::render_raw(&tpl, &params)?;
+
+        Ok(contents)
+    }
+
+    fn tsconfig_json(&self) -> String {
+        String::from_utf8_lossy(TSCONFIG_JSON_BIN).into_owned()
+    }
+
+    fn command_tsx(&self) -> Result<String> {
+        let mut params = Param::new();
+        params.insert("name", self.cargo_config.name());
+        params.insert("shim", &self.launcher_config.shim());
+        params.insert("bin", &self.launcher_config.bin());
+        params.insert("options", &self.cargo_config.run_options());
+        params.insert("preview", self.cargo_config.preview().as_str());
+        params.insert("debounce_ms", &self.cargo_config.debounce_ms());
+        params.insert("input", self.cargo_config.input().as_str());
+
+        let tpl = String::from_utf8_lossy(COMMAND_TSX_BIN).into_owned();
+        let contents = tpl::render_raw(&tpl, &params)?;
+
+        Ok(contents)
+    }
+
+    // Raycast expects a store handle as author, so the first author's name is slugified
+    fn author(&self) -> String {
+        let author = self.cargo_config.author();
+        let name = author
+            .split(&[',', '<'][..])
+            .next()
+            .unwrap_or("")
+            .trim()
+            .to_lowercase();
+        let slug = name
+            .chars()
+            .map(|c| if c.is_ascii_alphanumeric() { c } else { '-' })
+            .collect::<String>();
+        if slug.is_empty() {
+            String::from("cargo-launcher")
+        } else {
+            slug
+        }
+    }
+
+    fn icon(&self) -> Result<Vec<u8>> {
+        self.launcher_config.icon()
+    }
+}
+
+impl<'a> LauncherLike for Raycast<'a> {
+    fn before_check(&self) -> Result<()> {
+        Ok(())
+    }
+
+    fn gen(&self) -> Result<Vec<PathBuf>> {
+        mk_dir(self.extension_path("src"))?;
+        mk_dir(self.extension_path("assets"))?;
+
+        let package = self.extension_path("package.json");
+        write_file(&package, self.package_json()?.as_bytes())?;
+
+        let tsconfig = self.extension_path("tsconfig.json");
+        write_file(&tsconfig, self.tsconfig_json().as_bytes())?;
+
+        let command = self.extension_path(&format!("src/{}.tsx", self.cargo_config.name()));
+        write_file(&command, self.command_tsx()?.as_bytes())?;
+
+        let icon = self.extension_path("assets/icon.png");
+        write_file(&icon, &self.icon()?[..])?;
+
+        Ok(vec![package, tsconfig, command, icon])
+    }
+
+    fn deploy(&self, paths: Vec<PathBuf>) -> Result<()> {
+        let base = self.extension_dir();
+        let sink_dir = self.application_config()?;
+        for path in paths {
+            debug!("path: {:?}", &path);
+            debug!("sink: {:?}", &sink_dir);
+            let relative = path.strip_prefix(&base)?;
+            let sink = sink_dir.join(relative);
+            let parent = sink.parent().ok_or_else(|| err_msg("Not file type"))?;
+            fs::create_dir_all(parent)?;
+            fs::copy(&path, sink)?;
+        }
+        Ok(())
+    }
+
+    fn completed_message(&self) -> Result<String> {
+        let msg = r#"
+Install completed!!
+Run the following commands to import the extension into Raycast.
+
+  cd "#;
+        let path = self.application_config()?;
+        Ok(format!(
+            "{}{}\n  npm install && npm run dev",
+            msg,
+            path.to_string_lossy()
+        ))
+    }
+}
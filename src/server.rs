@@ -0,0 +1,471 @@
+use failure::*;
+use log::*;
+use serde_derive::*;
+use std::fs::OpenOptions;
+use std::io::{BufRead, BufReader, Write};
+use std::path::Path;
+use std::process::{Child, ChildStdin, Stdio};
+use std::sync::mpsc::{Receiver, RecvTimeoutError};
+use std::time::{Duration, Instant};
+#[cfg(unix)]
+use {
+    std::fs,
+    std::io::{self, ErrorKind},
+    std::os::unix::io::AsRawFd,
+    std::os::unix::net::{UnixListener, UnixStream},
+    std::path::PathBuf,
+    std::sync::mpsc,
+    std::thread,
+};
+
+use crate::args::RunArgs;
+use crate::cache;
+#[cfg(unix)]
+use crate::core::hash;
+use crate::error::Result;
+use crate::item::Item;
+use crate::shim;
+use crate::stream;
+use cargo_launcher_server::SERVER_ENV;
+
+// The host stops the binary and exits when no query comes for this
+#[cfg(unix)]
+const IDLE: Duration = Duration::from_secs(600);
+// The host is started by the first query, the shim waits until it listens
+#[cfg(unix)]
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(3);
+// The host answers the timeout of the binary, the shim waits a bit longer than it
+#[cfg(unix)]
+const TIMEOUT_MARGIN: Duration = Duration::from_secs(1);
+
+// How the binary is run for each query: once, kept running as server, or once for the list
+// filtered by the launcher
+value_enum! {
+    pub enum Mode: Run {
+        Run => "run",
+        Server => "server",
+        Filter => "filter",
+    }
+}
+
+// From the shim to the host, the args are the subcommand args and the split query
+#[derive(Serialize, Deserialize)]
+struct Request {
+    args: Vec<String>,
+    timeout_ms: Option<u64>,
+}
+
+// From the binary, or made by the host when the binary can't answer
+#[derive(Serialize, Deserialize, Default)]
+struct Response {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    id: Option<u64>,
+    #[serde(default)]
+    items: Vec<Item>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+    #[serde(default)]
+    timed_out: bool,
+}
+
+impl Response {
+    fn error(message: &str) -> Response {
+        Response {
+            error: Some(message.to_owned()),
+            ..Response::default()
+        }
+    }
+
+    fn timed_out() -> Response {
+        Response {
+            timed_out: true,
+            ..Response::default()
+        }
+    }
+}
+
+// The flag is false when the binary can't answer, the failures aren't cached
+pub fn items(args: &RunArgs, query: &[String]) -> (Vec<Item>, bool) {
+    let request = Request {
+        args: args.prefix_args.iter().chain(query).cloned().collect(),
+        timeout_ms: args.timeout_ms,
+    };
+    let response = send(args, &request).unwrap_or_else(|err| Response::error(&err.to_string()));
+    let line = shim::command_line(&shim::base_command(args), None);
+    if response.timed_out {
+        shim::log_failure(args, &line, "timed out", "");
+        let item = shim::timeout_item(args.timeout_ms.unwrap_or_default());
+        return (vec![item], false);
+    }
+    match response.error {
+        Some(err) => {
+            shim::log_failure(args, &line, "server error", &err);
+            (vec![shim::failed_item(args, None, &err)], false)
+        }
+        None => (response.items, true),
+    }
+}
+
+// One host per binary, env and cwd, the keywords of the subcommands share it
+#[cfg(unix)]
+fn socket_path(args: &RunArgs) -> Result<PathBuf> {
+    let key = shim::command_line(&shim::base_command(args), None);
+    Ok(cache::dir("server")?.join(format!("{}.sock", hash(&key))))
+}
+
+#[cfg(unix)]
+fn send(args: &RunArgs, request: &Request) -> Result<Response> {
+    let socket = socket_path(args)?;
+    let stream = match UnixStream::connect(&socket) {
+        Ok(stream) => stream,
+        Err(_) => {
+            // The host is the shim itself, it outlives this process until it's idle
+            shim::spawn_background("--serve", &socket)?;
+            connect(&socket)?
+        }
+    };
+    let timeout = request
+        .timeout_ms
+        .map(|timeout_ms| Duration::from_millis(timeout_ms) + TIMEOUT_MARGIN);
+    exchange(&stream, request, timeout)
+}
+
+// No unix socket, the binary is started per query and answers the one request
+#[cfg(not(unix))]
+fn send(args: &RunArgs, request: &Request) -> Result<Response> {
+    let mut binary = Binary::start(args)?;
+    let response = binary.ask(&request.args, request.timeout_ms.map(Duration::from_millis));
+    binary.stop();
+    Ok(response?.unwrap_or_else(Response::timed_out))
+}
+
+#[cfg(unix)]
+fn connect(socket: &Path) -> Result<UnixStream> {
+    let start = Instant::now();
+    loop {
+        match UnixStream::connect(socket) {
+            Ok(stream) => return Ok(stream),
+            Err(err) if start.elapsed() >= CONNECT_TIMEOUT => return Err(err.into()),
+            Err(_) => thread::sleep(Duration::from_millis(20)),
+        }
+    }
+}
+
+#[cfg(unix)]
+fn exchange(stream: &UnixStream, request: &Request, timeout: Option<Duration>) -> Result<Response> {
+    stream.set_read_timeout(timeout)?;
+    let mut writer = stream;
+    writeln!(writer, "{}", serde_json::to_string(request)?)?;
+
+    let mut line = String::new();
+    match BufReader::new(stream).read_line(&mut line) {
+        Ok(0) => bail!("The server host closed the connection"),
+        Ok(_) => Ok(serde_json::from_str(&line)?),
+        Err(ref err)
+            if err.kind() == ErrorKind::WouldBlock || err.kind() == ErrorKind::TimedOut =>
+        {
+            Ok(Response::timed_out())
+        }
+        Err(err) => Err(err.into()),
+    }
+}
+
+#[cfg(unix)]
+pub fn host(args: &RunArgs, socket: &Path) -> Result<String> {
+    let listener = match bind(socket)? {
+        Some(listener) => listener,
+        None => return Ok(String::new()),
+    };
+    serve(args, listener, IDLE);
+    let _lock = lock(socket)?;
+    fs::remove_file(socket)?;
+    Ok(String::new())
+}
+
+#[cfg(not(unix))]
+pub fn host(_: &RunArgs, _: &Path) -> Result<String> {
+    bail!("The server host needs unix domain sockets")
+}
+
+// Another host may be listening already, the socket left by a dead host is replaced
+#[cfg(unix)]
+fn bind(socket: &Path) -> Result<Option<UnixListener>> {
+    let _lock = lock(socket)?;
+    if UnixStream::connect(socket).is_ok() {
+        return Ok(None);
+    }
+    if socket.exists() {
+        fs::remove_file(socket)?;
+    }
+    Ok(Some(UnixListener::bind(socket)?))
+}
+
+// The hosts started by the concurrent queries bind and unlink the socket one by one, the lock is
+// released when the file is closed, also by a host that crashed
+#[cfg(unix)]
+fn lock(socket: &Path) -> Result<fs::File> {
+    let file = OpenOptions::new()
+        .create(true)
+        .truncate(false)
+        .write(true)
+        .open(socket.with_extension("lock"))?;
+    if unsafe { libc::flock(file.as_raw_fd(), libc::LOCK_EX) } != 0 {
+        return Err(io::Error::last_os_error().into());
+    }
+    Ok(file)
+}
+
+// The queries are answered one by one, the binary reads one request at a time
+#[cfg(unix)]
+fn serve(args: &RunArgs, listener: UnixListener, idle: Duration) {
+    let (tx, rx) = mpsc::channel();
+    thread::spawn(move || {
+        for stream in listener.incoming() {
+            if tx.send(stream).is_err() {
+                break;
+            }
+        }
+    });
+
+    let mut binary = None;
+    while let Ok(stream) = rx.recv_timeout(idle) {
+        let result = stream
+            .map_err(Error::from)
+            .and_then(|stream| handle(args, &mut binary, &stream));
+        if let Err(err) = result {
+            debug!("server: {}", err);
+        }
+    }
+    if let Some(mut binary) = binary {
+        binary.stop();
+    }
+}
+
+#[cfg(unix)]
+fn handle(args: &RunArgs, binary: &mut Option<Binary>, stream: &UnixStream) -> Result<()> {
+    stream.set_read_timeout(Some(CONNECT_TIMEOUT))?;
+    let mut line = String::new();
+    BufReader::new(stream).read_line(&mut line)?;
+    let request = serde_json::from_str::<Request>(&line)?;
+
+    let response = ask(args, binary, &request);
+    let mut writer = stream;
+    writeln!(writer, "{}", serde_json::to_string(&response)?)?;
+    Ok(())
+}
+
+// The binary is started again when it's not running, and the request is retried once when it dies
+#[cfg_attr(not(unix), allow(dead_code))]
+fn ask(args: &RunArgs, binary: &mut Option<Binary>, request: &Request) -> Response {
+    let timeout = request.timeout_ms.map(Duration::from_millis);
+    let mut last_error = String::new();
+    for _ in 0..2 {
+        let alive = binary
+            .take()
+            .and_then(|mut current| match current.running() {
+                true => Some(current),
+                false => None,
+            });
+        let mut current = match alive {
+            Some(current) => current,
+            None => match Binary::start(args) {
+                Ok(current) => current,
+                Err(err) => return Response::error(&err.to_string()),
+            },
+        };
+        match current.ask(&request.args, timeout) {
+            Ok(Some(response)) => {
+                *binary = Some(current);
+                return response;
+            }
+            Ok(None) => {
+                current.stop();
+                return Response::timed_out();
+            }
+            Err(err) => {
+                current.stop();
+                last_error = err.to_string();
+            }
+        }
+    }
+    Response::error(&last_error)
+}
+
+struct Binary {
+    child: Child,
+    stdin: ChildStdin,
+    lines: Receiver<String>,
+    id: u64,
+}
+
+impl Binary {
+    // The stderr of the binary goes to the log, the stdout is for the responses
+    fn start(args: &RunArgs) -> Result<Binary> {
+        let log = cache::log_path(&format!("{}-server", shim::bin_name(args)))?;
+        let log = OpenOptions::new().create(true).append(true).open(log)?;
+        let mut child = shim::base_command(args)
+            .env(SERVER_ENV, "1")
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::from(log))
+            .spawn()?;
+        debug!("server binary: {}", child.id());
+        let stdin = child
+            .stdin
+            .take()
+            .ok_or_else(|| err_msg("Notfound stdin"))?;
+        let lines = stream::lines(child.stdout.take());
+        Ok(Binary {
+            child,
+            stdin,
+            lines,
+            id: 0,
+        })
+    }
+
+    fn running(&mut self) -> bool {
+        matches!(self.child.try_wait(), Ok(None))
+    }
+
+    // Returns None when the binary doesn't answer in the timeout
+    fn ask(&mut self, args: &[String], timeout: Option<Duration>) -> Result<Option<Response>> {
+        self.id += 1;
+        let request = cargo_launcher_server::Request {
+            id: self.id,
+            args: args.to_vec(),
+        };
+        writeln!(self.stdin, "{}", serde_json::to_string(&request)?)?;
+        self.stdin.flush()?;
+
+        let deadline = timeout.map(|timeout| Instant::now() + timeout);
+        loop {
+            let line = match deadline {
+                Some(deadline) => self
+                    .lines
+                    .recv_timeout(deadline.saturating_duration_since(Instant::now())),
+                None => self
+                    .lines
+                    .recv()
+                    .map_err(|_| RecvTimeoutError::Disconnected),
+            };
+            let line = match line {
+                Ok(line) => line,
+                Err(RecvTimeoutError::Timeout) => return Ok(None),
+                Err(RecvTimeoutError::Disconnected) => bail!("The server binary exited"),
+            };
+            match serde_json::from_str::<Response>(&line) {
+                // The late answers of the timed out requests are skipped
+                Ok(response) if response.id.map_or(true, |id| id == self.id) => {
+                    return Ok(Some(response))
+                }
+                Ok(_) => continue,
+                Err(err) => debug!("server: {}: {}", err, line),
+            }
+        }
+    }
+
+    fn stop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+#[cfg(test)]
+#[cfg(unix)]
+mod tests {
+
+    use crate::fs::write_file;
+    use crate::server::*;
+    use std::os::unix::fs::PermissionsExt;
+    use tempdir::TempDir;
+
+    fn script(tmp_dir: &TempDir, body: &str) -> RunArgs {
+        let path = tmp_dir.path().join("server-bin");
+        write_file(&path, format!("#!/bin/sh\n{}\n", body).as_bytes()).unwrap();
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o755)).unwrap();
+        RunArgs::dummy(&path.to_string_lossy())
+    }
+
+    fn request(args: &[&str], timeout_ms: Option<u64>) -> Request {
+        Request {
+            args: args.iter().map(|arg| String::from(*arg)).collect(),
+            timeout_ms,
+        }
+    }
+
+    #[test]
+    fn ask_restart_ok() {
+        let tmp_dir = TempDir::new("ask_restart_ok").unwrap();
+        // Answers one request and exits
+        let args = script(
+            &tmp_dir,
+            r#"read line; echo "{\"items\": [{\"title\": \"$CARGO_LAUNCHER_SERVER\"}]}""#,
+        );
+        let mut binary = None;
+        for _ in 0..2 {
+            let actual = ask(&args, &mut binary, &request(&["a"], Some(3000)));
+            assert_eq!(actual.items, vec![Item::text("1")]);
+        }
+    }
+
+    #[test]
+    fn ask_skip_stale_ok() {
+        let tmp_dir = TempDir::new("ask_skip_stale_ok").unwrap();
+        let args = script(
+            &tmp_dir,
+            r#"read line; echo '{"id": 99}'; echo 'debug'; echo '{"id": 1, "items": [{"title": "b"}]}'; read line"#,
+        );
+        let mut binary = None;
+        let actual = ask(&args, &mut binary, &request(&[], Some(3000)));
+        assert_eq!(actual.items, vec![Item::text("b")]);
+        binary.unwrap().stop();
+    }
+
+    #[test]
+    fn ask_timeout_ok() {
+        let tmp_dir = TempDir::new("ask_timeout_ok").unwrap();
+        let args = script(&tmp_dir, "read line; exec sleep 5");
+        let mut binary = None;
+        let actual = ask(&args, &mut binary, &request(&[], Some(100)));
+        assert!(actual.timed_out);
+        assert!(binary.is_none());
+    }
+
+    #[test]
+    fn serve_socket_ok() {
+        let tmp_dir = TempDir::new("serve_socket_ok").unwrap();
+        let args = script(
+            &tmp_dir,
+            r#"while read line; do echo '{"items": [{"title": "pong"}]}'; done"#,
+        );
+        let socket = tmp_dir.path().join("test.sock");
+        let listener = bind(&socket).unwrap().unwrap();
+        let handle = thread::spawn(move || serve(&args, listener, Duration::from_millis(500)));
+
+        for _ in 0..2 {
+            let stream = UnixStream::connect(&socket).unwrap();
+            let actual = exchange(&stream, &request(&["ping"], None), None).unwrap();
+            assert_eq!(actual.items, vec![Item::text("pong")]);
+        }
+        // The second host doesn't bind while the first one is listening
+        assert!(bind(&socket).unwrap().is_none());
+        handle.join().unwrap();
+    }
+
+    #[test]
+    fn bind_locked_ok() {
+        let tmp_dir = TempDir::new("bind_locked_ok").unwrap();
+        let socket = tmp_dir.path().join("test.sock");
+        let lock = lock(&socket).unwrap();
+        let handle = {
+            let socket = socket.clone();
+            thread::spawn(move || bind(&socket).unwrap().is_some())
+        };
+        // The socket isn't touched while another host holds the lock
+        thread::sleep(Duration::from_millis(200));
+        assert!(!handle.is_finished());
+        assert!(!socket.exists());
+        drop(lock);
+        assert!(handle.join().unwrap());
+    }
+}
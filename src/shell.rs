@@ -0,0 +1,173 @@
+use failure::*;
+use log::*;
+use std::fs;
+use std::path::PathBuf;
+
+use crate::cargo::CargoConfig;
+use crate::error::Result;
+use crate::fs::write_file;
+use crate::launcher::{LauncherConfig, LauncherLike};
+use crate::tpl::{self, Param};
+
+const BASH_BIN: &[u8] = include_bytes!("asset/shell/widget.bash");
+const ZSH_BIN: &[u8] = include_bytes!("asset/shell/widget.zsh");
+const FISH_BIN: &[u8] = include_bytes!("asset/shell/widget.fish");
+const SHELLS: [(&str, &[u8]); 3] = [("bash", BASH_BIN), ("zsh", ZSH_BIN), ("fish", FISH_BIN)];
+
+pub struct Shell<'a> {
+    cargo_config: &'a CargoConfig,
+    launcher_config: &'a LauncherConfig<'a>,
+}
+
+impl<'a> Shell<'a> {
+    pub fn new(cargo_config: &'a CargoConfig, launcher_config: &'a LauncherConfig) -> Shell<'a> {
+        Shell {
+            cargo_config,
+            launcher_config,
+        }
+    }
+
+    fn application_config(&self) -> Result<PathBuf> {
+        let mut path = dirs::data_dir().ok_or_else(|| err_msg("Notfound data dir"))?;
+        path.push("cargo-launcher/shell");
+        Ok(path)
+    }
+
+    fn file_name(&self, ext: &str) -> String {
+        format!("{}.{}", self.cargo_config.name(), ext)
+    }
+
+    fn widget_path(&self, ext: &str) -> PathBuf {
+        let mut buf = self.launcher_config.work_dir.clone();
+        buf.push(self.file_name(ext));
+        buf
+    }
+
+    fn widget(&self, ext: &str, tpl: &[u8]) -> Result<String> {
+        let conf = self.cargo_config;
+        let mut installed = self.application_config()?;
+        installed.push(self.file_name(ext));
+
+        let mut params = Param::new();
+        params.insert("name", conf.name());
+        params.insert("func", &self.func_name());
+        params.insert("version", conf.version());
+        params.insert("description", conf.description());
+        params.insert("path", &installed.to_string_lossy());
+        // Fish unescapes the backslashes in the single quotes, the POSIX quoting doesn't fit it
+        let quote: fn(&str) -> String = match ext {
+            "fish" => fish_quote,
+            _ => |word: &str| shell_words::quote(word).into_owned(),
+        };
+        let options = conf
+            .run_options()
+            .iter()
+            .map(|option| quote(option))
+            .collect::<Vec<_>>();
+        params.insert("shim", &quote(&self.launcher_config.shim()));
+        params.insert("bin", &quote(&self.launcher_config.bin()));
+        params.insert("options", &options.join(" "));
+
+        let tpl = String::from_utf8_lossy(tpl).into_owned();
+        let contents = tpl::render_raw(&tpl, &params)?;
+
+        Ok(contents)
+    }
+
+    fn func_name(&self) -> String {
+        self.cargo_config
+            .name()
+            .chars()
+            .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+            .collect()
+    }
+}
+
+fn fish_quote(word: &str) -> String {
+    let safe = |c: char| c.is_ascii_alphanumeric() || "_-./=:,+@".contains(c);
+    if !word.is_empty() && word.chars().all(safe) {
+        return word.to_owned();
+    }
+    format!("'{}'", word.replace('\\', "\\\\").replace('\'', "\\'"))
+}
+
+impl<'a> LauncherLike for Shell<'a> {
+    fn before_check(&self) -> Result<()> {
+        if cfg!(target_os = "windows") {
+            bail!("Shell widget supported only unix")
+        }
+        Ok(())
+    }
+
+    fn gen(&self) -> Result<Vec<PathBuf>> {
+        let mut paths = vec![];
+        for (ext, tpl) in SHELLS.iter() {
+            let path = self.widget_path(ext);
+            write_file(&path, self.widget(ext, tpl)?.as_bytes())?;
+            paths.push(path);
+        }
+        Ok(paths)
+    }
+
+    fn deploy(&self, paths: Vec<PathBuf>) -> Result<()> {
+        let sink_dir = self.application_config()?;
+        fs::create_dir_all(&sink_dir)?;
+        for path in paths {
+            debug!("path: {:?}", &path);
+            debug!("sink: {:?}", &sink_dir);
+            let name = path.file_name().ok_or_else(|| err_msg("Not file type"))?;
+            let mut sink = sink_dir.clone();
+            sink.push(name);
+            fs::copy(&path, sink)?;
+        }
+        Ok(())
+    }
+
+    fn completed_message(&self) -> Result<String> {
+        let dir = self.application_config()?;
+        let lines = SHELLS
+            .iter()
+            .map(|(ext, _)| {
+                let mut path = dir.clone();
+                path.push(self.file_name(ext));
+                format!("  {:<5} source {}", ext, path.to_string_lossy())
+            })
+            .collect::<Vec<String>>()
+            .join("\n");
+
+        let msg = format!(
+            r#"
+Install completed!!
+Add the line for your shell to its rc file (~/.bashrc, ~/.zshrc or ~/.config/fish/config.fish).
+Then press Ctrl-X Ctrl-L to run {} and insert the output into the command line.
+
+{}"#,
+            self.cargo_config.name(),
+            lines
+        );
+        Ok(msg)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use crate::shell::*;
+
+    #[test]
+    fn func_name_ok() {
+        let cargo_config = CargoConfig::dummy("test-bin");
+        let launcher_config = LauncherConfig::dummy();
+        let shell = Shell::new(&cargo_config, &launcher_config);
+        assert_eq!(shell.func_name(), "test_bin");
+    }
+
+    #[test]
+    fn fish_quote_ok() {
+        assert_eq!(fish_quote("--tokenize"), "--tokenize");
+        assert_eq!(fish_quote(""), "''");
+        assert_eq!(fish_quote("a b"), "'a b'");
+        assert_eq!(fish_quote(r"it's"), r"'it\'s'");
+        assert_eq!(fish_quote(r"a\b\"), r"'a\\b\\'");
+    }
+}
@@ -0,0 +1,217 @@
+use failure::*;
+use log::*;
+use serde_json::{json, Value};
+use std::path::PathBuf;
+
+use crate::cargo::CargoConfig;
+use crate::error::Result;
+use crate::fs::{backup_file, mk_dir, read_file, write_file};
+use crate::launcher::{LauncherConfig, LauncherLike};
+
+const LAUNCHER_TYPE: &str = "bulk_text";
+const KEYWORD: &str = "{keyword}";
+// Only the entries with the marker are replaced, the entries written by hand are kept even with the same name
+const MARKER: &str = "cargo_launcher";
+
+pub struct Sherlock<'a> {
+    cargo_config: &'a CargoConfig,
+    launcher_config: &'a LauncherConfig<'a>,
+}
+
+impl<'a> Sherlock<'a> {
+    pub fn new(cargo_config: &'a CargoConfig, launcher_config: &'a LauncherConfig) -> Sherlock<'a> {
+        Sherlock {
+            cargo_config,
+            launcher_config,
+        }
+    }
+
+    fn application_config(&self) -> Result<PathBuf> {
+        let mut path = dirs::config_dir().ok_or_else(|| err_msg("Notfound config dir"))?;
+        path.push("sherlock/fallback.json");
+        Ok(path)
+    }
+
+    fn entry_path(&self) -> PathBuf {
+        let mut buf = self.launcher_config.work_dir.clone();
+        buf.push(format!("{}.sherlock.json", self.cargo_config.name()));
+        buf
+    }
+
+    // Sherlock runs the exec without a shell, the exec-args are split by the spaces after the keyword is put,
+    // so the words of the keyword are the own arguments, the shim joins them again and tokenizes by the configured mode
+    fn exec_args(&self) -> Result<String> {
+        let mut args = vec![String::from("launcher"), String::from("run")];
+        args.extend(vec![String::from("--launcher"), String::from("sherlock")]);
+        args.extend(self.cargo_config.run_options());
+        args.extend(vec![String::from("--"), self.launcher_config.bin()]);
+        if let Some(arg) = args.iter().find(|arg| arg.is_empty() || arg.contains(' ')) {
+            bail!(
+                "Sherlock splits the arguments by the spaces, can't pass {:?}",
+                arg
+            )
+        }
+        args.push(String::from(KEYWORD));
+        Ok(args.join(" "))
+    }
+
+    fn entry(&self) -> Result<Value> {
+        let name = self.cargo_config.name();
+        let exec_args = self.exec_args()?;
+        let entry = json!({
+            "name": name,
+            "alias": name,
+            "type": LAUNCHER_TYPE,
+            "args": {
+                "icon": "utilities-terminal",
+                "exec": self.launcher_config.shim(),
+                "exec-args": exec_args
+            },
+            "priority": 0,
+            "async": true,
+            MARKER: true
+        });
+        Ok(entry)
+    }
+
+    // The entry written by the previous install is recognized by its marker and name
+    fn is_managed(&self, entry: &Value) -> bool {
+        entry[MARKER] == true && entry["name"] == self.cargo_config.name()
+    }
+}
+
+// The empty config is the empty list, the other values than the list are left to the user
+fn entries(contents: &[u8]) -> Result<Vec<Value>> {
+    if String::from_utf8_lossy(contents).trim().is_empty() {
+        return Ok(vec![]);
+    }
+    match serde_json::from_slice(contents)? {
+        Value::Array(entries) => Ok(entries),
+        _ => bail!("The Sherlock fallback.json isn't a list of the launchers"),
+    }
+}
+
+impl<'a> LauncherLike for Sherlock<'a> {
+    fn before_check(&self) -> Result<()> {
+        if cfg!(not(target_os = "linux")) {
+            bail!("Sherlock supported only linux")
+        }
+        Ok(())
+    }
+
+    fn gen(&self) -> Result<Vec<PathBuf>> {
+        let entry = self.entry_path();
+        write_file(
+            &entry,
+            serde_json::to_string_pretty(&self.entry()?)?.as_bytes(),
+        )?;
+
+        Ok(vec![entry])
+    }
+
+    fn deploy(&self, paths: Vec<PathBuf>) -> Result<()> {
+        let config = self.application_config()?;
+        let parent = config.parent().ok_or_else(|| err_msg("Not file type"))?;
+        mk_dir(parent)?;
+        let backup = backup_file(&config)?;
+        debug!("config: {:?}", &config);
+        debug!("backup: {:?}", &backup);
+
+        let mut entries = if config.exists() {
+            entries(&read_file(&config)?)?
+        } else {
+            vec![]
+        };
+        entries.retain(|e| !self.is_managed(e));
+        for path in paths {
+            debug!("path: {:?}", &path);
+            entries.push(serde_json::from_slice(&read_file(&path)?)?);
+        }
+        write_file(&config, serde_json::to_string_pretty(&entries)?.as_bytes())?;
+        Ok(())
+    }
+
+    fn completed_message(&self) -> Result<String> {
+        let msg = format!(
+            r#"
+Install completed!!
+Restart of the sherlock is required, then type "{} " to run.
+
+Installed path: "#,
+            self.cargo_config.name()
+        );
+        let path = self.application_config()?;
+        Ok(format!("{}{}", msg, path.to_string_lossy()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use crate::sherlock::*;
+    use std::path::PathBuf;
+
+    #[test]
+    fn exec_args_injection_ok() {
+        let cargo_config = CargoConfig::dummy("test-bin");
+        let launcher_config = LauncherConfig::dummy();
+        let sherlock = Sherlock::new(&cargo_config, &launcher_config);
+        // Same as Sherlock, the keyword is put and the args are split by the spaces
+        let exec_args = sherlock.exec_args().unwrap();
+        let keyword = "x'; rm -rf ~; $(id)";
+        let actual = exec_args
+            .replace(KEYWORD, keyword)
+            .split(' ')
+            .map(String::from)
+            .collect::<Vec<String>>();
+        let expected = vec![
+            "launcher",
+            "run",
+            "--launcher",
+            "sherlock",
+            "--tokenize",
+            "whitespace",
+            "--",
+            "/usr/local/bin/test-bin",
+            "x';",
+            "rm",
+            "-rf",
+            "~;",
+            "$(id)",
+        ];
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn is_managed_ok() {
+        let cargo_config = CargoConfig::dummy("test-bin");
+        let launcher_config = LauncherConfig::dummy();
+        let sherlock = Sherlock::new(&cargo_config, &launcher_config);
+        let entry = sherlock.entry().unwrap();
+        assert_eq!(entry["cargo_launcher"], true);
+        assert!(sherlock.is_managed(&entry));
+        let by_hand = json!({ "name": "test-bin", "type": LAUNCHER_TYPE });
+        assert!(!sherlock.is_managed(&by_hand));
+        let other = json!({ "name": "other-bin", "type": LAUNCHER_TYPE, MARKER: true });
+        assert!(!sherlock.is_managed(&other));
+    }
+
+    #[test]
+    fn entries_ok() {
+        assert!(entries(b"").unwrap().is_empty());
+        assert!(entries(b" \n").unwrap().is_empty());
+        assert!(entries(b"{}").is_err());
+        assert!(entries(b"\"a\"").is_err());
+        assert_eq!(entries(b"[{\"name\": \"a\"}]").unwrap().len(), 1);
+        assert!(entries(b"[").is_err());
+    }
+
+    #[test]
+    fn exec_args_space_ng() {
+        let cargo_config = CargoConfig::dummy("test-bin");
+        let mut launcher_config = LauncherConfig::dummy();
+        launcher_config.bin_path = PathBuf::from("/opt/my tools/test-bin");
+        let sherlock = Sherlock::new(&cargo_config, &launcher_config);
+        assert!(sherlock.exec_args().is_err());
+    }
+}
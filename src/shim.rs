@@ -0,0 +1,1002 @@
+use failure::*;
+use log::*;
+use serde_derive::*;
+use serde_json::{json, Value};
+use std::env;
+use std::ffi::{OsStr, OsString};
+use std::io::{self, Read, Write};
+use std::path::Path;
+use std::process::{Child, Command, Stdio};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use crate::args::RunArgs;
+use crate::cache;
+use crate::complete;
+use crate::core::hash;
+use crate::error::Result;
+use crate::filter;
+use crate::history;
+use crate::input::{self, Input};
+use crate::item::{self, Action, ActionKind, Item};
+use crate::launcher::Launcher;
+use crate::mime;
+use crate::notify::{self, OutputMode};
+use crate::preview::{self, PreviewFormat};
+use crate::server::{self, Mode};
+use crate::stream;
+use crate::usage::{self, EmptyQuery};
+
+const SUCCESS: &str = "Success";
+const COPY_STDERR_TITLE: &str = "Copy stderr";
+const ALFRED_MODS: [&str; 2] = ["cmd", "alt"];
+// The list of the filter mode is read again after this, unless the cache ttl is set
+const FILTER_TTL_SECS: u64 = 60;
+
+value_enum! {
+    pub enum Tokenize: Whitespace {
+        Whole => "whole",
+        Whitespace => "whitespace",
+        Shell => "shell",
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct Output {
+    pub code: Option<i32>,
+    pub stdout: String,
+    pub stderr: String,
+    pub timed_out: bool,
+    // The stdout that isn't text, e.g. an image, the stdout is empty then
+    #[serde(skip)]
+    pub binary: Option<Vec<u8>>,
+}
+
+impl Output {
+    pub fn success(&self) -> bool {
+        self.code == Some(0)
+    }
+
+    pub fn stdout(&self) -> &str {
+        &self.stdout
+    }
+
+    pub fn summary(&self) -> String {
+        match (self.timed_out, self.code) {
+            (true, _) => String::from("timed out"),
+            (false, Some(code)) => format!("exit code: {}", code),
+            (false, None) => String::from("killed by signal"),
+        }
+    }
+}
+
+pub fn run(args: &mut RunArgs) -> Result<String> {
+    if let Some(socket) = &args.serve {
+        return server::host(args, socket);
+    }
+    if args.query_stdin {
+        args.query = vec![read_query(&mut io::stdin())?];
+        args.query_stdin = false;
+    }
+    let args: &RunArgs = args;
+    if let Some(title) = &args.select {
+        history::select(args, title);
+        return Ok(String::new());
+    }
+    let joined = args.query.join(" ");
+    // Alfred runs the filter mode once with the empty query, and filters the list by itself
+    let alfred_filter = args.mode == Mode::Filter && matches!(args.launcher, Launcher::Alfred);
+    let items = match args.empty_query {
+        _ if !joined.trim().is_empty() || alfred_filter => None,
+        EmptyQuery::Run => None,
+        EmptyQuery::Usage => Some(usage::items(args)),
+        EmptyQuery::Recent => Some(usage::recent_items(args)),
+    };
+    if let Some(items) = items {
+        return format(&args.launcher, &resolve(args, items), args.preview);
+    }
+    // The background runner of Alfred has the query of the shim that started it
+    let recording = args.history || args.empty_query == EmptyQuery::Recent;
+    if recording && args.stream_dir.is_none() {
+        history::record(args, &joined);
+    }
+    // The background runner of Alfred reads the input from the dir, the shim that started it wrote it
+    let input = match &args.stream_dir {
+        Some(_) => None,
+        None => match input::read(args) {
+            Ok(input) => input,
+            Err(err) => {
+                let item = Item {
+                    subtitle: Some(err.to_string()),
+                    valid: false,
+                    icon: error_icon(),
+                    ..Item::text("Failed to read the input")
+                };
+                return format(&args.launcher, &resolve(args, vec![item]), args.preview);
+            }
+        },
+    };
+    let input = input.as_deref();
+    // The binary lists every item without the query, they're filtered by the query here
+    if args.mode == Mode::Filter {
+        let ttl = Some(Duration::from_secs(FILTER_TTL_SECS));
+        let (items, success) = fetch(args, &[], input, ttl);
+        // Alfred filters the results by itself(alfredfiltersresults)
+        let items = match args.launcher {
+            Launcher::Alfred => items,
+            _ if success => filter::filter(items, &joined),
+            _ => items,
+        };
+        let items = ranked(args, items);
+        return format(&args.launcher, &resolve(args, items), args.preview);
+    }
+    // The query is the input itself, it isn't passed as the arguments
+    let query = match args.input {
+        Input::StdinFromQuery => Ok(vec![]),
+        _ => tokenize(&args.query, args.tokenize),
+    };
+    let query = match query {
+        Ok(query) => query,
+        Err(err) => {
+            let item = Item {
+                subtitle: Some(err.to_string()),
+                valid: false,
+                icon: error_icon(),
+                ..Item::text("Invalid query")
+            };
+            return format(&args.launcher, &resolve(args, vec![item]), args.preview);
+        }
+    };
+    // The actions run once on the submit, Shell and Espanso run the binary once per input anyway
+    if args.output == OutputMode::Notify {
+        let once = matches!(args.launcher, Launcher::Shell | Launcher::Espanso);
+        if args.submit || once {
+            return notify::submit(args, command(args, &query), input);
+        }
+        let items = vec![notify::prompt(args, &joined)];
+        return format(&args.launcher, &resolve(args, items), args.preview);
+    }
+    if let Some(dir) = &args.stream_dir {
+        return stream::write(args, command(args, &query), dir);
+    }
+    // The streamed results are shown as they come, they're never cached
+    if args.stream && args.mode == Mode::Run && stream::supported(&args.launcher) {
+        return stream::run(args, command(args, &query), input);
+    }
+
+    if !args.complete {
+        let (items, _) = fetch(args, &query, input, None);
+        let items = ranked(args, items);
+        return format(&args.launcher, &resolve(args, items), args.preview);
+    }
+    // The partial word usually fails the binary, the candidates are shown instead of the failure
+    let mut items = complete::candidates(args, &query);
+    let (found, success) = fetch(args, &query, input, None);
+    if success || items.is_empty() {
+        items.extend(ranked(args, found));
+    }
+    format(&args.launcher, &resolve(args, items), args.preview)
+}
+
+// The here-document ends the query with the newline, the query itself is one line
+fn read_query<R: Read>(reader: &mut R) -> Result<String> {
+    let mut query = String::new();
+    reader.read_to_string(&mut query)?;
+    if query.ends_with('\n') {
+        query.pop();
+    }
+    Ok(query)
+}
+
+// The results selected often come first, the binary doesn't know the selections
+fn ranked(args: &RunArgs, items: Vec<Item>) -> Vec<Item> {
+    if args.history {
+        history::rank(args, items)
+    } else {
+        items
+    }
+}
+
+// The flag is false when the binary failed, the failures aren't cached
+fn fetch(
+    args: &RunArgs,
+    query: &[String],
+    input: Option<&str>,
+    default_ttl: Option<Duration>,
+) -> (Vec<Item>, bool) {
+    let cmd = command(args, query);
+    let line = command_line(&cmd, input);
+    let ttl = args.cache_ttl_secs.map(Duration::from_secs).or(default_ttl);
+    if let Some(items) = ttl.and_then(|ttl| cached_items(args, &line, ttl)) {
+        return (items, true);
+    }
+    let (items, success) = match args.mode {
+        // The server reads the queries from the socket, the binary with the input runs per query
+        Mode::Server if input.is_none() => server::items(args, query),
+        _ => match exec_input(cmd, args.timeout_ms.map(Duration::from_millis), input) {
+            Ok(output) => {
+                if !output.success() {
+                    log_failure(args, &line, &output.summary(), &output.stderr);
+                }
+                (items(args, &output), output.success())
+            }
+            Err(err) => {
+                log_failure(args, &line, "spawn failed", &err.to_string());
+                (vec![failed_item(args, None, &err.to_string())], false)
+            }
+        },
+    };
+    if success && ttl.is_some() {
+        cache_items(args, &line, &items);
+    }
+    (items, success)
+}
+
+// The key of the cache and the line of the log, e.g. `cd /tmp && 'KEY=value' bin 'a b' < 123`
+// The input is a part of the key, the same query gets other results for other clipboard
+pub fn command_line(cmd: &Command, input: Option<&str>) -> String {
+    let lossy = |s: &OsStr| s.to_string_lossy().into_owned();
+    let mut envs = cmd
+        .get_envs()
+        .map(|(key, value)| format!("{}={}", lossy(key), value.map(lossy).unwrap_or_default()))
+        .collect::<Vec<String>>();
+    envs.sort();
+    let mut words = envs;
+    words.push(lossy(cmd.get_program()));
+    words.extend(cmd.get_args().map(lossy));
+    let mut line = shell_words::join(words);
+    if let Some(cwd) = cmd.get_current_dir() {
+        line = format!(
+            "cd {} && {}",
+            shell_words::quote(&lossy(cwd.as_os_str())),
+            line
+        );
+    }
+    if let Some(input) = input {
+        line = format!("{} < {}", line, hash(input));
+    }
+    line
+}
+
+// The command line is the key, it has the args, env and cwd of the binary
+fn cached_items(args: &RunArgs, key: &str, ttl: Duration) -> Option<Vec<Item>> {
+    let dir = cache::result_dir(&bin_name(args)).ok()?;
+    cache::read_items(&dir, key, ttl)
+}
+
+fn cache_items(args: &RunArgs, key: &str, items: &[Item]) {
+    let result = cache::result_dir(&bin_name(args))
+        .and_then(|dir| cache::write_items(&dir, key, items, args.cache_max_entries));
+    if let Err(err) = result {
+        debug!("cache: {}", err);
+    }
+}
+
+// Launchers pass the query as one or more strings, they are joined and split again by the mode
+fn tokenize(query: &[String], mode: Tokenize) -> Result<Vec<String>> {
+    let joined = query.join(" ");
+    let args = match mode {
+        Tokenize::Whole if joined.is_empty() => vec![],
+        Tokenize::Whole => vec![joined],
+        Tokenize::Whitespace => joined.split_whitespace().map(String::from).collect(),
+        Tokenize::Shell => shell_words::split(&joined)?,
+    };
+    Ok(args)
+}
+
+fn command(args: &RunArgs, query: &[String]) -> Command {
+    let mut cmd = base_command(args);
+    cmd.args(&args.prefix_args).args(query);
+    cmd
+}
+
+// The binary with the env and cwd of the metadata, without the arguments
+pub fn base_command(args: &RunArgs) -> Command {
+    let mut cmd = Command::new(&args.bin);
+    cmd.envs(args.env.iter().map(|(key, value)| (key, value)));
+    if let Some(cwd) = &args.cwd {
+        cmd.current_dir(cwd);
+    }
+    cmd
+}
+
+// Runs the shim itself again in background with the same arguments and the option, returns the pid
+pub fn spawn_background(option: &str, path: &Path) -> Result<u32> {
+    let mut args = env::args_os().skip(1).collect::<Vec<OsString>>();
+    let pos = args
+        .iter()
+        .position(|arg| arg == "run")
+        .map_or(0, |pos| pos + 1);
+    args.insert(pos, path.as_os_str().to_owned());
+    args.insert(pos, OsString::from(option));
+
+    let child = Command::new(env::current_exe()?)
+        .args(args)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()?;
+    debug!("background: {}", child.id());
+    Ok(child.id())
+}
+
+pub fn exec(cmd: Command, timeout: Option<Duration>) -> Result<Output> {
+    exec_input(cmd, timeout, None)
+}
+
+// The input is written to the stdin, the stdin is null without it
+pub fn exec_input(
+    mut cmd: Command,
+    timeout: Option<Duration>,
+    input: Option<&str>,
+) -> Result<Output> {
+    debug!("exec: {:?}", cmd);
+
+    let mut child = spawn_input(cmd.stdout(Stdio::piped()).stderr(Stdio::piped()), input)?;
+    // The pipes are drained while waiting, or a chatty child blocks on a full pipe
+    let stdout = drain_bytes(child.stdout.take());
+    let stderr = drain(child.stderr.take());
+
+    let code = match wait(&mut child, timeout)? {
+        Some(code) => code,
+        None => {
+            child.kill()?;
+            child.wait()?;
+            // The pipes aren't joined, the grandchildren may still hold them open
+            return Ok(Output {
+                code: None,
+                stdout: String::new(),
+                stderr: String::new(),
+                timed_out: true,
+                binary: None,
+            });
+        }
+    };
+
+    // The lossy text mangles the binary output, it's kept as is
+    let stdout = stdout.join().unwrap_or_default();
+    let (stdout, binary) = match mime::sniff(&stdout) {
+        Some(_) => (String::new(), Some(stdout)),
+        None => (String::from_utf8_lossy(&stdout).into_owned(), None),
+    };
+    Ok(Output {
+        code,
+        stdout,
+        stderr: stderr.join().unwrap_or_default(),
+        timed_out: false,
+        binary,
+    })
+}
+
+// Written in a thread, the binary may not read the stdin before it prints a lot
+pub fn spawn_input(cmd: &mut Command, input: Option<&str>) -> Result<Child> {
+    let input = match input {
+        Some(input) => input.to_owned(),
+        None => return Ok(cmd.stdin(Stdio::null()).spawn()?),
+    };
+    let mut child = cmd.stdin(Stdio::piped()).spawn()?;
+    if let Some(mut stdin) = child.stdin.take() {
+        thread::spawn(move || {
+            // The binary that doesn't read the stdin closes the pipe, it isn't an error
+            if let Err(err) = stdin.write_all(input.as_bytes()) {
+                debug!("stdin: {}", err);
+            }
+        });
+    }
+    Ok(child)
+}
+
+pub fn drain<R: Read + Send + 'static>(pipe: Option<R>) -> thread::JoinHandle<String> {
+    thread::spawn(move || {
+        let mut buf = vec![];
+        if let Some(mut pipe) = pipe {
+            let _ = pipe.read_to_end(&mut buf);
+        }
+        String::from_utf8_lossy(&buf).into_owned()
+    })
+}
+
+fn drain_bytes<R: Read + Send + 'static>(pipe: Option<R>) -> thread::JoinHandle<Vec<u8>> {
+    thread::spawn(move || {
+        let mut buf = vec![];
+        if let Some(mut pipe) = pipe {
+            let _ = pipe.read_to_end(&mut buf);
+        }
+        buf
+    })
+}
+
+// Returns None when the child is still running after the timeout
+pub fn wait(child: &mut Child, timeout: Option<Duration>) -> Result<Option<Option<i32>>> {
+    let timeout = match timeout {
+        Some(timeout) => timeout,
+        None => return Ok(Some(child.wait()?.code())),
+    };
+    let start = Instant::now();
+    loop {
+        if let Some(status) = child.try_wait()? {
+            return Ok(Some(status.code()));
+        }
+        if start.elapsed() >= timeout {
+            return Ok(None);
+        }
+        thread::sleep(Duration::from_millis(10));
+    }
+}
+
+pub fn items(args: &RunArgs, output: &Output) -> Vec<Item> {
+    if output.timed_out {
+        return vec![timeout_item(args.timeout_ms.unwrap_or_default())];
+    }
+    if output.success() {
+        return match &output.binary {
+            Some(binary) => mime::items(args, binary),
+            None => item::parse(&output.stdout),
+        };
+    }
+    // Some tools print the errors to stdout, it's used when stderr is empty
+    let details = if output.stderr.trim().is_empty() {
+        &output.stdout
+    } else {
+        &output.stderr
+    };
+    vec![failed_item(args, output.code, details)]
+}
+
+// The same error item is shown by every launcher, the full details are copied by the action
+pub fn failed_item(args: &RunArgs, code: Option<i32>, details: &str) -> Item {
+    let first_line = details
+        .lines()
+        .map(str::trim)
+        .find(|line| !line.is_empty())
+        .unwrap_or_default();
+    let subtitle = match (code, first_line) {
+        (Some(code), "") => format!("Exit code {}", code),
+        (Some(code), line) => format!("Exit code {}: {}", code, line),
+        (None, "") => String::from("Failed"),
+        (None, line) => line.to_owned(),
+    };
+    let details = details.trim();
+    let actions = if details.is_empty() {
+        vec![]
+    } else {
+        vec![Action {
+            title: String::from(COPY_STDERR_TITLE),
+            arg: details.to_owned(),
+            kind: ActionKind::Copy,
+        }]
+    };
+    Item {
+        subtitle: Some(subtitle),
+        icon: error_icon(),
+        preview: Some(details.to_owned()).filter(|details| !details.is_empty()),
+        valid: false,
+        actions,
+        ..Item::text(&format!("{} failed", bin_name(args)))
+    }
+}
+
+pub fn bin_name(args: &RunArgs) -> String {
+    Path::new(&args.bin)
+        .file_stem()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_else(|| args.bin.clone())
+}
+
+fn error_icon() -> Option<String> {
+    match cache::error_icon() {
+        Ok(path) => Some(path.to_string_lossy().into_owned()),
+        Err(err) => {
+            debug!("error icon: {}", err);
+            None
+        }
+    }
+}
+
+// One log per generated plugin, e.g. ~/.cache/cargo-launcher/log/<bin>-alfred.log
+pub fn log_failure(args: &RunArgs, command: &str, summary: &str, details: &str) {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|now| now.as_secs())
+        .unwrap_or_default();
+    let name = format!("{}-{}", bin_name(args), args.launcher).to_lowercase();
+    let entry = format!("[{}] {}: {}\n{}", now, summary, command, details);
+    if let Err(err) = cache::append_log(&name, &entry) {
+        debug!("log: {}", err);
+    }
+}
+
+// The default action of the metadata is applied, and rerun gets the keyword to search again
+pub fn resolve(args: &RunArgs, items: Vec<Item>) -> Vec<Item> {
+    items
+        .into_iter()
+        .map(|mut item| {
+            let action = item.action.unwrap_or(args.action);
+            if action == ActionKind::Rerun {
+                item.arg = Some(rerun_query(args, item.arg()));
+            }
+            for action in &mut item.actions {
+                if action.kind == ActionKind::Rerun {
+                    action.arg = rerun_query(args, &action.arg);
+                }
+            }
+            item.autocomplete = item
+                .autocomplete
+                .as_ref()
+                .map(|query| rerun_query(args, query));
+            item.action = Some(action);
+            item
+        })
+        .collect()
+}
+
+// Alfred, Hain and Albert are searched again with the whole input, the others with the query
+fn rerun_query(args: &RunArgs, arg: &str) -> String {
+    match (&args.launcher, &args.keyword) {
+        (Launcher::Hain, Some(keyword)) => format!("/{} {}", keyword, arg),
+        (Launcher::Alfred, Some(keyword)) | (Launcher::Albert, Some(keyword)) => {
+            format!("{} {}", keyword, arg)
+        }
+        _ => arg.to_owned(),
+    }
+}
+
+pub fn timeout_item(timeout_ms: u64) -> Item {
+    Item {
+        subtitle: Some(format!("Timed out after {} ms", timeout_ms)),
+        icon: error_icon(),
+        valid: false,
+        ..Item::text("Timed out")
+    }
+}
+
+pub fn format(launcher: &Launcher, items: &[Item], preview: PreviewFormat) -> Result<String> {
+    let value = match launcher {
+        Launcher::Alfred => alfred(items, preview),
+        Launcher::Hain => hain(items, preview),
+        Launcher::Albert => albert(items),
+        Launcher::Raycast | Launcher::Hammerspoon => json!({ "items": items }),
+        Launcher::Espanso | Launcher::Shell | Launcher::Walker | Launcher::Sherlock => {
+            return plain(items)
+        }
+    };
+    Ok(serde_json::to_string(&value)?)
+}
+
+// One item per line for the streaming plugins, Alfred is streamed by rerun so it never comes here
+pub fn format_line(launcher: &Launcher, item: &Item, preview: PreviewFormat) -> Result<String> {
+    let value = match launcher {
+        Launcher::Alfred => alfred_item(item, preview),
+        Launcher::Hain => hain_item(item, preview),
+        Launcher::Albert => albert_item(item),
+        Launcher::Raycast | Launcher::Hammerspoon => json!(item),
+        Launcher::Espanso | Launcher::Shell | Launcher::Walker | Launcher::Sherlock => {
+            return plain(std::slice::from_ref(item))
+        }
+    };
+    Ok(serde_json::to_string(&value)?)
+}
+
+// Text based launchers can't show an error item, so the failure is reported by the exit code
+fn plain(items: &[Item]) -> Result<String> {
+    if let Some(failed) = items.iter().find(|item| !item.valid) {
+        bail!("{}: {}", failed.title, subtitle(failed))
+    }
+    let lines = items.iter().map(Item::arg).collect::<Vec<&str>>();
+    Ok(lines.join("\n"))
+}
+
+fn subtitle(item: &Item) -> &str {
+    item.subtitle.as_deref().unwrap_or(SUCCESS)
+}
+
+pub fn alfred(items: &[Item], preview: PreviewFormat) -> Value {
+    let items = items
+        .iter()
+        .map(|item| alfred_item(item, preview))
+        .collect::<Vec<Value>>();
+    json!({ "items": items })
+}
+
+fn alfred_item(item: &Item, preview: PreviewFormat) -> Value {
+    let arg = item.arg();
+    let mut value = json!({
+        "title": item.title,
+        "subtitle": subtitle(item),
+        "arg": arg,
+        "valid": item.valid,
+        "text": { "copy": arg, "largetype": arg },
+        "variables": { "action": item.action().as_str(), "title": item.title },
+    });
+    if let Some(icon) = &item.icon {
+        value["icon"] = json!({ "path": icon });
+    }
+    if let Some(autocomplete) = &item.autocomplete {
+        value["autocomplete"] = json!(autocomplete);
+    }
+    // Quick Look(shift or cmd+Y) shows the whole output of the item
+    match preview::write(&preview::html(item.preview(), preview)) {
+        Ok(path) => value["quicklookurl"] = json!(path),
+        Err(err) => debug!("preview: {}", err),
+    }
+    for (action, modifier) in item.actions.iter().zip(ALFRED_MODS.iter()) {
+        value["mods"][*modifier] = json!({
+            "subtitle": action.title,
+            "arg": action.arg,
+            "valid": true,
+            "variables": { "action": action.kind.as_str() },
+        });
+    }
+    value
+}
+
+fn hain(items: &[Item], preview: PreviewFormat) -> Value {
+    let items = items
+        .iter()
+        .map(|item| hain_item(item, preview))
+        .collect::<Vec<Value>>();
+    json!(items)
+}
+
+fn hain_item(item: &Item, preview: PreviewFormat) -> Value {
+    // Hain runs one action per item, an invalid item runs its first extra action if any
+    let (id, arg) = match (item.valid, item.actions.first()) {
+        (true, _) => (item.action().as_str(), item.arg()),
+        (false, Some(action)) => (action.kind.as_str(), action.arg.as_str()),
+        (false, None) => ("invalid", item.arg()),
+    };
+    let mut value = json!({
+        "id": id,
+        "payload": {
+            "arg": arg,
+            "title": item.title,
+            "html": preview::html(item.preview(), preview),
+        },
+        "preview": item.valid || item.preview.is_some(),
+        "title": format!("<b>{}</b>", preview::escape_html(&item.title)),
+        "desc": preview::escape_html(subtitle(item)),
+    });
+    if let Some(icon) = &item.icon {
+        value["icon"] = json!(icon);
+    }
+    // Hain redirects the query instead of the execute, so only the invalid items get it
+    if let (false, Some(autocomplete)) = (item.valid, &item.autocomplete) {
+        value["redirect"] = json!(autocomplete);
+    }
+    value
+}
+
+fn albert(items: &[Item]) -> Value {
+    let items = items.iter().map(albert_item).collect::<Vec<Value>>();
+    json!(items)
+}
+
+fn albert_item(item: &Item) -> Value {
+    let mut actions = vec![];
+    if item.valid {
+        let kind = item.action();
+        actions.push(json!({ "text": item::title(kind), "arg": item.arg(), "type": kind }));
+    }
+    for action in &item.actions {
+        actions.push(json!({ "text": action.title, "arg": action.arg, "type": action.kind }));
+    }
+    let mut value = json!({
+        "text": item.title,
+        "subtext": subtitle(item),
+        "actions": actions,
+    });
+    if let Some(icon) = &item.icon {
+        value["icon"] = json!(icon);
+    }
+    if let Some(autocomplete) = &item.autocomplete {
+        value["completion"] = json!(autocomplete);
+    }
+    value
+}
+
+#[cfg(test)]
+#[cfg(not(target_os = "windows"))]
+mod tests {
+
+    use crate::item::Action;
+    use crate::shim::*;
+    use std::path::PathBuf;
+
+    fn output(code: i32, stdout: &str, stderr: &str) -> Output {
+        Output {
+            code: Some(code),
+            stdout: stdout.to_owned(),
+            stderr: stderr.to_owned(),
+            timed_out: false,
+            binary: None,
+        }
+    }
+
+    fn run_args(bin: &str) -> RunArgs {
+        RunArgs::dummy(bin)
+    }
+
+    fn query(args: &[&str]) -> Vec<String> {
+        args.iter().map(|arg| String::from(*arg)).collect()
+    }
+
+    #[test]
+    fn tokenize_whole_ok() {
+        let actual = tokenize(&query(&["a  b", "c"]), Tokenize::Whole).unwrap();
+        assert_eq!(actual, query(&["a  b c"]));
+        let actual = tokenize(&query(&[]), Tokenize::Whole).unwrap();
+        assert!(actual.is_empty());
+    }
+
+    #[test]
+    fn tokenize_whitespace_ok() {
+        let actual = tokenize(&query(&["a  b", "c"]), Tokenize::Whitespace).unwrap();
+        assert_eq!(actual, query(&["a", "b", "c"]));
+    }
+
+    #[test]
+    fn tokenize_shell_ok() {
+        let actual = tokenize(&query(&[r#"a "b c" d\ e; rm"#]), Tokenize::Shell).unwrap();
+        assert_eq!(actual, query(&["a", "b c", "d e;", "rm"]));
+    }
+
+    #[test]
+    fn tokenize_shell_unterminated_ng() {
+        let actual = tokenize(&query(&[r#"a "b"#]), Tokenize::Shell);
+        assert!(actual.is_err());
+    }
+
+    #[test]
+    fn read_query_ok() {
+        let actual = read_query(&mut "x'; rm -rf ~; $(id)\n".as_bytes()).unwrap();
+        assert_eq!(actual, "x'; rm -rf ~; $(id)");
+        assert_eq!(read_query(&mut "".as_bytes()).unwrap(), "");
+    }
+
+    #[test]
+    fn command_line_ok() {
+        let args = RunArgs {
+            env: vec![
+                (String::from("B"), String::from("b c")),
+                (String::from("A"), String::from("a")),
+            ],
+            cwd: Some(PathBuf::from("/tmp")),
+            ..run_args("test-bin")
+        };
+        let cmd = command(&args, &query(&["a b", "c"]));
+        let actual = command_line(&cmd, None);
+        assert_eq!(actual, "cd /tmp && 'A=a' 'B=b c' test-bin 'a b' c");
+        let actual = command_line(&cmd, Some("input"));
+        assert_eq!(
+            actual,
+            format!(
+                "cd /tmp && 'A=a' 'B=b c' test-bin 'a b' c < {}",
+                hash("input")
+            )
+        );
+    }
+
+    #[test]
+    fn exec_echo_ok() {
+        let cmd = command(&run_args("echo"), &query(&["a", "$(id)", "; ls"]));
+        let actual = exec(cmd, None).unwrap();
+        assert!(actual.success());
+        assert_eq!(actual.stdout, "a $(id) ; ls\n");
+    }
+
+    #[test]
+    fn exec_input_ok() {
+        let actual = exec_input(Command::new("cat"), None, Some("a\nb")).unwrap();
+        assert_eq!(actual.stdout, "a\nb");
+        // The stdin isn't read, the binary ends without waiting for it
+        let actual = exec_input(Command::new("true"), None, Some("a")).unwrap();
+        assert!(actual.success());
+    }
+
+    #[test]
+    fn exec_notfound_ng() {
+        let cmd = command(&run_args("cargo-launcher-notfound-bin"), &[]);
+        let actual = exec(cmd, None);
+        assert!(actual.is_err());
+    }
+
+    #[test]
+    fn exec_env_cwd_ok() {
+        let args = RunArgs {
+            env: vec![(String::from("TEST_ENV"), String::from("a=b"))],
+            cwd: Some(PathBuf::from("/")),
+            ..run_args("sh")
+        };
+        let cmd = command(&args, &query(&["-c", "echo $TEST_ENV; pwd"]));
+        let actual = exec(cmd, None).unwrap();
+        assert_eq!(actual.stdout, "a=b\n/\n");
+    }
+
+    #[test]
+    fn exec_timeout_ok() {
+        let cmd = command(&run_args("sleep"), &query(&["5"]));
+        let start = Instant::now();
+        let actual = exec(cmd, Some(Duration::from_millis(100))).unwrap();
+        assert!(actual.timed_out);
+        assert!(start.elapsed() < Duration::from_secs(5));
+
+        let args = RunArgs {
+            timeout_ms: Some(100),
+            ..run_args("sleep")
+        };
+        let actual = items(&args, &actual);
+        assert_eq!(actual[0].title, "Timed out");
+        assert!(!actual[0].valid);
+    }
+
+    #[test]
+    fn items_success_ok() {
+        let actual = items(&run_args("test-bin"), &output(0, "a\nb\n", ""));
+        assert_eq!(actual, vec![Item::text("a"), Item::text("b")]);
+    }
+
+    #[test]
+    fn items_failed_ok() {
+        let args = run_args("/usr/local/bin/test-bin");
+        let actual = items(&args, &output(2, "", "\nerror: invalid\nusage"));
+        assert_eq!(actual.len(), 1);
+        assert_eq!(actual[0].title, "test-bin failed");
+        assert_eq!(
+            actual[0].subtitle,
+            Some(String::from("Exit code 2: error: invalid"))
+        );
+        assert!(actual[0].icon.as_ref().unwrap().ends_with("error.png"));
+        assert!(!actual[0].valid);
+        assert_eq!(actual[0].actions[0].title, COPY_STDERR_TITLE);
+        assert_eq!(actual[0].actions[0].arg, "error: invalid\nusage");
+    }
+
+    #[test]
+    fn items_failed_stdout_ok() {
+        let actual = items(&run_args("test-bin"), &output(1, "not found\n", ""));
+        assert_eq!(
+            actual[0].subtitle,
+            Some(String::from("Exit code 1: not found"))
+        );
+        assert_eq!(actual[0].actions[0].arg, "not found");
+    }
+
+    #[test]
+    fn items_failed_empty_ok() {
+        let actual = items(&run_args("test-bin"), &output(3, "", ""));
+        assert_eq!(actual[0].subtitle, Some(String::from("Exit code 3")));
+        assert!(actual[0].actions.is_empty());
+    }
+
+    #[test]
+    fn resolve_action_ok() {
+        let args = RunArgs {
+            launcher: Launcher::Hain,
+            action: ActionKind::Rerun,
+            keyword: Some(String::from("test-bin")),
+            ..run_args("test-bin")
+        };
+        let items = vec![
+            Item::text("a"),
+            Item {
+                action: Some(ActionKind::Url),
+                ..Item::text("b")
+            },
+        ];
+        let actual = resolve(&args, items);
+        assert_eq!(actual[0].action(), ActionKind::Rerun);
+        assert_eq!(actual[0].arg(), "/test-bin a");
+        assert_eq!(actual[1].action(), ActionKind::Url);
+        assert_eq!(actual[1].arg(), "b");
+    }
+
+    #[test]
+    fn format_plain_ok() {
+        let actual = format(
+            &Launcher::Shell,
+            &[Item::text("a"), Item::text("b")],
+            PreviewFormat::Text,
+        )
+        .unwrap();
+        assert_eq!(actual, "a\nb");
+    }
+
+    #[test]
+    fn format_plain_failed_ng() {
+        let actual = format(
+            &Launcher::Espanso,
+            &[failed_item(&run_args("test-bin"), Some(1), "error")],
+            PreviewFormat::Text,
+        );
+        assert!(actual.is_err());
+    }
+
+    #[test]
+    fn format_alfred_ok() {
+        let item = Item {
+            icon: Some(String::from("icon.png")),
+            action: Some(ActionKind::Url),
+            actions: vec![Action {
+                title: String::from("other"),
+                arg: String::from("b"),
+                kind: ActionKind::Reveal,
+            }],
+            ..Item::text("a")
+        };
+        let actual: Value =
+            serde_json::from_str(&format(&Launcher::Alfred, &[item], PreviewFormat::Text).unwrap())
+                .unwrap();
+        let item = &actual["items"][0];
+        assert_eq!(item["title"], "a");
+        assert_eq!(item["subtitle"], SUCCESS);
+        assert_eq!(item["arg"], "a");
+        assert_eq!(item["icon"]["path"], "icon.png");
+        assert_eq!(item["variables"]["action"], "url");
+        assert_eq!(item["mods"]["cmd"]["arg"], "b");
+        assert_eq!(item["mods"]["cmd"]["variables"]["action"], "reveal");
+        assert!(item["quicklookurl"].as_str().unwrap().ends_with(".html"));
+    }
+
+    #[test]
+    fn format_hain_ok() {
+        let item = Item {
+            valid: false,
+            ..Item::text("<a>")
+        };
+        let actual: Value =
+            serde_json::from_str(&format(&Launcher::Hain, &[item], PreviewFormat::Text).unwrap())
+                .unwrap();
+        assert_eq!(actual[0]["id"], "invalid");
+        assert_eq!(actual[0]["title"], "<b>&lt;a&gt;</b>");
+        assert_eq!(actual[0]["payload"]["arg"], "<a>");
+        assert!(actual[0]["payload"]["html"]
+            .as_str()
+            .unwrap()
+            .contains("<pre>&lt;a&gt;</pre>"));
+    }
+
+    #[test]
+    fn format_albert_ok() {
+        let actual: Value = serde_json::from_str(
+            &format(&Launcher::Albert, &[Item::text("a")], PreviewFormat::Text).unwrap(),
+        )
+        .unwrap();
+        assert_eq!(actual[0]["text"], "a");
+        assert_eq!(
+            actual[0]["actions"][0]["text"],
+            item::title(ActionKind::Copy)
+        );
+        assert_eq!(actual[0]["actions"][0]["type"], "copy");
+        assert_eq!(actual[0]["actions"][0]["arg"], "a");
+    }
+
+    #[test]
+    fn format_albert_invalid_ok() {
+        let item = Item {
+            valid: false,
+            ..Item::text("a")
+        };
+        let actual: Value =
+            serde_json::from_str(&format(&Launcher::Albert, &[item], PreviewFormat::Text).unwrap())
+                .unwrap();
+        assert_eq!(actual[0]["actions"].as_array().unwrap().len(), 0);
+    }
+
+    #[test]
+    fn format_failed_ok() {
+        let item = failed_item(&run_args("test-bin"), Some(1), "error: a\nb");
+        let actual: Value =
+            serde_json::from_str(&format(&Launcher::Albert, &[item], PreviewFormat::Text).unwrap())
+                .unwrap();
+        assert_eq!(actual[0]["actions"][0]["text"], COPY_STDERR_TITLE);
+        assert_eq!(actual[0]["actions"][0]["arg"], "error: a\nb");
+
+        let item = failed_item(&run_args("test-bin"), Some(1), "error: a\nb");
+        let actual: Value =
+            serde_json::from_str(&format(&Launcher::Hain, &[item], PreviewFormat::Text).unwrap())
+                .unwrap();
+        assert_eq!(actual[0]["id"], "copy");
+        assert_eq!(actual[0]["payload"]["arg"], "error: a\nb");
+        assert_eq!(actual[0]["desc"], "Exit code 1: error: a");
+    }
+}
@@ -0,0 +1,447 @@
+use log::*;
+use serde_json::json;
+use std::fs::{self, OpenOptions};
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::path::Path;
+use std::process::{Command, Stdio};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::args::RunArgs;
+use crate::cache;
+use crate::core::hash;
+use crate::error::Result;
+use crate::fs::{read_file, write_file};
+use crate::item::{self, Item, Line};
+use crate::launcher::Launcher;
+use crate::shim::{self, Output};
+
+// Alfred runs the script filter again after the seconds while the binary is running
+const ALFRED_RERUN_SEC: f64 = 0.2;
+// The finished runs nobody read(the query was changed) are removed after this
+const STALE: Duration = Duration::from_secs(10);
+const OUT_FILE: &str = "out";
+const STATUS_FILE: &str = "status";
+const INPUT_FILE: &str = "input";
+const PID_FILE: &str = "pid";
+
+// Alfred can't read a running process, so it's streamed by rerun instead of one item per line
+pub fn supported(launcher: &Launcher) -> bool {
+    matches!(
+        launcher,
+        Launcher::Alfred
+            | Launcher::Hain
+            | Launcher::Albert
+            | Launcher::Raycast
+            | Launcher::Hammerspoon
+    )
+}
+
+// Prints one item per line as the binary prints them, the plugins add them to the list as they come
+pub fn run(args: &RunArgs, cmd: Command, input: Option<&str>) -> Result<String> {
+    if let Launcher::Alfred = args.launcher {
+        return alfred(args, cmd, input);
+    }
+
+    let line = shim::command_line(&cmd, input);
+    let stdout = io::stdout();
+    let mut out = stdout.lock();
+    let mut emit = |items: Vec<Item>| -> Result<()> {
+        for item in shim::resolve(args, items) {
+            let line = shim::format_line(&args.launcher, &item, args.preview)?;
+            writeln!(out, "{}", line)?;
+        }
+        out.flush()?;
+        Ok(())
+    };
+
+    let result = follow(cmd, timeout(args), input, |line| {
+        match item::parse_line(line) {
+            Line::Items(items) => emit(items).map(|_| true),
+            Line::Done => Ok(false),
+        }
+    });
+    emit(end_items(args, &line, result))?;
+    Ok(String::new())
+}
+
+// Background runner of Alfred, the lines are appended to the out file and the status is written at the end
+pub fn write(args: &RunArgs, cmd: Command, dir: &Path) -> Result<String> {
+    let mut out = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(dir.join(OUT_FILE))?;
+    let input = read_file(dir.join(INPUT_FILE))
+        .ok()
+        .map(|input| String::from_utf8_lossy(&input).into_owned());
+    let result = follow(cmd, timeout(args), input.as_deref(), |line| {
+        writeln!(out, "{}", line)?;
+        Ok(match item::parse_line(line) {
+            Line::Items(_) => true,
+            Line::Done => false,
+        })
+    });
+    let output = match result {
+        Ok(Some(output)) => output,
+        Ok(None) => Output {
+            code: Some(0),
+            stdout: String::new(),
+            stderr: String::new(),
+            timed_out: false,
+            binary: None,
+        },
+        Err(err) => Output {
+            code: None,
+            stdout: String::new(),
+            stderr: err.to_string(),
+            timed_out: false,
+            binary: None,
+        },
+    };
+    // Renamed at once, the rerun never reads a half written status
+    let tmp = dir.join(format!("{}.tmp", STATUS_FILE));
+    write_file(&tmp, serde_json::to_string(&output)?.as_bytes())?;
+    fs::rename(&tmp, dir.join(STATUS_FILE))?;
+    Ok(String::new())
+}
+
+fn alfred(args: &RunArgs, cmd: Command, input: Option<&str>) -> Result<String> {
+    let line = shim::command_line(&cmd, input);
+    let root = cache::dir("stream")?;
+    sweep(&root)?;
+    let dir = root.join(hash(&line).to_string());
+    let out = dir.join(OUT_FILE);
+    if !out.exists() {
+        fs::create_dir_all(&dir)?;
+        if let Some(input) = input {
+            write_file(dir.join(INPUT_FILE), input.as_bytes())?;
+        }
+        write_file(&out, b"")?;
+        // The runner is the shim itself, it outlives this process until the binary ends
+        let pid = shim::spawn_background("--stream-dir", &dir)?;
+        write_file(dir.join(PID_FILE), pid.to_string().as_bytes())?;
+    }
+
+    // The status is read first, the out file is complete when it exists
+    let status = match read_file(dir.join(STATUS_FILE)) {
+        Ok(status) => Some(serde_json::from_slice::<Output>(&status)?),
+        Err(_) => None,
+    };
+    let mut items = read_items(&String::from_utf8_lossy(&read_file(&out)?));
+    match status {
+        Some(output) => {
+            items.extend(end_items(args, &line, Ok(Some(output))));
+            fs::remove_dir_all(&dir)?;
+            shim::format(&args.launcher, &shim::resolve(args, items), args.preview)
+        }
+        None => {
+            if items.is_empty() {
+                items.push(running_item(args));
+            }
+            let mut value = shim::alfred(&shim::resolve(args, items), args.preview);
+            value["rerun"] = json!(ALFRED_RERUN_SEC);
+            Ok(serde_json::to_string(&value)?)
+        }
+    }
+}
+
+// The finished runs nobody read, and the runs whose runner is gone without the status(killed,
+// crashed or rebooted), the query runs again then
+fn sweep(root: &Path) -> Result<()> {
+    for entry in fs::read_dir(root)? {
+        let dir = entry?.path();
+        let stale = match older(&dir.join(STATUS_FILE)) {
+            Some(stale) => stale,
+            None => abandoned(&dir),
+        };
+        // Another shim may have removed it already
+        if stale {
+            match fs::remove_dir_all(&dir) {
+                Err(err) if err.kind() != io::ErrorKind::NotFound => return Err(err.into()),
+                _ => (),
+            }
+        }
+    }
+    Ok(())
+}
+
+// None when the file doesn't exist
+fn older(path: &Path) -> Option<bool> {
+    let modified = fs::metadata(path).and_then(|m| m.modified()).ok()?;
+    Some(modified.elapsed().is_ok_and(|elapsed| elapsed > STALE))
+}
+
+// The runner is checked before the status, it can't write the status once it's gone
+fn abandoned(dir: &Path) -> bool {
+    let running = match read_file(dir.join(PID_FILE)) {
+        Ok(pid) => String::from_utf8_lossy(&pid)
+            .trim()
+            .parse()
+            .is_ok_and(running),
+        // The spawning shim writes the pid right after the out file
+        Err(_) => older(&dir.join(OUT_FILE)) == Some(false),
+    };
+    !running && !dir.join(STATUS_FILE).exists()
+}
+
+#[cfg(unix)]
+fn running(pid: i32) -> bool {
+    unsafe { libc::kill(pid, 0) == 0 }
+}
+
+// Alfred runs only on macOS
+#[cfg(not(unix))]
+fn running(_: i32) -> bool {
+    true
+}
+
+// Only the complete lines, the last one may be in the middle of writing
+fn read_items(text: &str) -> Vec<Item> {
+    let complete = text.rfind('\n').map_or("", |pos| &text[..pos]);
+    let mut items = vec![];
+    for line in complete.lines() {
+        match item::parse_line(line) {
+            Line::Items(parsed) => items.extend(parsed),
+            Line::Done => break,
+        }
+    }
+    items
+}
+
+fn running_item(args: &RunArgs) -> Item {
+    Item {
+        subtitle: Some(String::from("Waiting for the results")),
+        valid: false,
+        ..Item::text(&format!("{} is running", shim::bin_name(args)))
+    }
+}
+
+fn timeout(args: &RunArgs) -> Option<Duration> {
+    args.timeout_ms.map(Duration::from_millis)
+}
+
+// The output is None when the binary ended the results by {"done": true}
+fn end_items(args: &RunArgs, line: &str, result: Result<Option<Output>>) -> Vec<Item> {
+    match result {
+        Ok(Some(output)) if !output.success() => {
+            shim::log_failure(args, line, &output.summary(), &output.stderr);
+            // The stdout is already shown as the items
+            let output = Output {
+                stdout: String::new(),
+                ..output
+            };
+            shim::items(args, &output)
+        }
+        Ok(_) => vec![],
+        Err(err) => {
+            shim::log_failure(args, line, "spawn failed", &err.to_string());
+            vec![shim::failed_item(args, None, &err.to_string())]
+        }
+    }
+}
+
+// Calls on_line per line as the binary prints it, until it returns false or the binary ends
+fn follow<F>(
+    mut cmd: Command,
+    timeout: Option<Duration>,
+    input: Option<&str>,
+    mut on_line: F,
+) -> Result<Option<Output>>
+where
+    F: FnMut(&str) -> Result<bool>,
+{
+    debug!("follow: {:?}", cmd);
+
+    let mut child = shim::spawn_input(cmd.stdout(Stdio::piped()).stderr(Stdio::piped()), input)?;
+    let lines = lines(child.stdout.take());
+    let stderr = shim::drain(child.stderr.take());
+    let deadline = timeout.map(|timeout| Instant::now() + timeout);
+
+    let timed_out = Output {
+        code: None,
+        stdout: String::new(),
+        stderr: String::new(),
+        timed_out: true,
+        binary: None,
+    };
+    loop {
+        let line = match deadline {
+            Some(deadline) => {
+                lines.recv_timeout(deadline.saturating_duration_since(Instant::now()))
+            }
+            None => lines.recv().map_err(|_| RecvTimeoutError::Disconnected),
+        };
+        match line {
+            // The binary may still run after done, it's left to exit by itself
+            Ok(line) if !on_line(&line)? => return Ok(None),
+            Ok(_) => continue,
+            Err(RecvTimeoutError::Timeout) => {
+                child.kill()?;
+                child.wait()?;
+                return Ok(Some(timed_out));
+            }
+            Err(RecvTimeoutError::Disconnected) => break,
+        }
+    }
+
+    let remaining = deadline.map(|deadline| deadline.saturating_duration_since(Instant::now()));
+    match shim::wait(&mut child, remaining)? {
+        Some(code) => Ok(Some(Output {
+            code,
+            stdout: String::new(),
+            stderr: stderr.join().unwrap_or_default(),
+            timed_out: false,
+            binary: None,
+        })),
+        None => {
+            child.kill()?;
+            child.wait()?;
+            Ok(Some(timed_out))
+        }
+    }
+}
+
+pub fn lines<R: Read + Send + 'static>(pipe: Option<R>) -> Receiver<String> {
+    let (tx, rx) = mpsc::channel();
+    thread::spawn(move || {
+        let mut reader = match pipe {
+            Some(pipe) => BufReader::new(pipe),
+            None => return,
+        };
+        let mut buf = vec![];
+        while reader.read_until(b'\n', &mut buf).unwrap_or(0) > 0 {
+            let line = String::from_utf8_lossy(&buf);
+            if tx
+                .send(line.trim_end_matches(&['\r', '\n'][..]).to_owned())
+                .is_err()
+            {
+                break;
+            }
+            buf.clear();
+        }
+    });
+    rx
+}
+
+#[cfg(test)]
+#[cfg(not(target_os = "windows"))]
+mod tests {
+
+    use crate::stream::*;
+    use tempdir::TempDir;
+
+    fn sh(script: &str) -> Command {
+        let mut cmd = Command::new("sh");
+        cmd.args(["-c", script]);
+        cmd
+    }
+
+    #[test]
+    fn follow_lines_ok() {
+        let mut actual = vec![];
+        let output = follow(
+            sh("echo a; echo b >&2; echo c; exit 3"),
+            None,
+            None,
+            |line| {
+                actual.push(line.to_owned());
+                Ok(true)
+            },
+        )
+        .unwrap()
+        .unwrap();
+        assert_eq!(actual, vec!["a", "c"]);
+        assert_eq!(output.code, Some(3));
+        assert_eq!(output.stderr, "b\n");
+    }
+
+    #[test]
+    fn follow_input_ok() {
+        let mut actual = vec![];
+        follow(sh("tr a-z A-Z"), None, Some("a\nb"), |line| {
+            actual.push(line.to_owned());
+            Ok(true)
+        })
+        .unwrap();
+        assert_eq!(actual, vec!["A", "B"]);
+    }
+
+    #[test]
+    fn follow_done_ok() {
+        let mut actual = vec![];
+        let start = Instant::now();
+        let output = follow(
+            sh(r#"echo a; echo '{"done": true}'; exec sleep 5 >/dev/null"#),
+            None,
+            None,
+            |line| {
+                actual.push(line.to_owned());
+                Ok(match item::parse_line(line) {
+                    Line::Items(_) => true,
+                    Line::Done => false,
+                })
+            },
+        )
+        .unwrap();
+        assert!(output.is_none());
+        assert_eq!(actual.len(), 2);
+        assert!(start.elapsed() < Duration::from_secs(5));
+    }
+
+    #[test]
+    fn follow_timeout_ok() {
+        let mut actual = vec![];
+        let output = follow(
+            sh("echo a; exec sleep 5"),
+            Some(Duration::from_millis(200)),
+            None,
+            |line| {
+                actual.push(line.to_owned());
+                Ok(true)
+            },
+        )
+        .unwrap()
+        .unwrap();
+        assert_eq!(actual, vec!["a"]);
+        assert!(output.timed_out);
+    }
+
+    #[test]
+    fn sweep_abandoned_ok() {
+        let tmp_dir = TempDir::new("sweep_abandoned_ok").unwrap();
+        let root = tmp_dir.path();
+        let mut exited = Command::new("true").spawn().unwrap();
+        exited.wait().unwrap();
+        let runs = [
+            ("exited", Some(exited.id()), false),
+            ("running", Some(std::process::id()), false),
+            ("spawning", None, false),
+            ("finished", Some(exited.id()), true),
+        ];
+        for (name, pid, finished) in &runs {
+            let dir = root.join(name);
+            fs::create_dir_all(&dir).unwrap();
+            write_file(dir.join(OUT_FILE), b"a\n").unwrap();
+            if let Some(pid) = pid {
+                write_file(dir.join(PID_FILE), pid.to_string().as_bytes()).unwrap();
+            }
+            if *finished {
+                write_file(dir.join(STATUS_FILE), b"{}").unwrap();
+            }
+        }
+        sweep(root).unwrap();
+        assert!(!root.join("exited").exists());
+        assert!(root.join("running").exists());
+        assert!(root.join("spawning").exists());
+        assert!(root.join("finished").exists());
+    }
+
+    #[test]
+    fn read_items_partial_ok() {
+        let actual = read_items("a\n{\"title\": \"b\"}\nc");
+        assert_eq!(actual, vec![Item::text("a"), Item::text("b")]);
+        let actual = read_items("a\n{\"done\": true}\nb\n");
+        assert_eq!(actual, vec![Item::text("a")]);
+    }
+}
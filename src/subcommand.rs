@@ -0,0 +1,214 @@
+use log::*;
+use serde_derive::*;
+use std::path::Path;
+use std::process::Command;
+use std::time::Duration;
+
+use crate::cargo::CargoConfig;
+use crate::error::Result;
+use crate::shim;
+
+const HELP_TIMEOUT_MS: u64 = 3000;
+// clap 2 prints "SUBCOMMANDS:", clap 3 or later prints "Commands:"
+const HELP_HEADERS: [&str; 2] = ["subcommands:", "commands:"];
+
+// One keyword(trigger) of the generated plugin, the args are passed before the query
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Subcommand {
+    pub keyword: String,
+    #[serde(default)]
+    pub title: String,
+    #[serde(default)]
+    pub description: String,
+    #[serde(default)]
+    pub args: Vec<String>,
+}
+
+impl Subcommand {
+    pub fn run_options(&self, cargo_config: &CargoConfig) -> Vec<String> {
+        let mut options = cargo_config.run_options_with(&self.description);
+        options.push(String::from("--keyword"));
+        options.push(self.keyword.clone());
+        for arg in &self.args {
+            options.push(String::from("--prefix-arg"));
+            options.push(arg.clone());
+        }
+        options
+    }
+}
+
+// The commands listed in the metadata win, otherwise they are derived from the help of the binary
+pub fn resolve(cargo_config: &CargoConfig, bin_path: &Path) -> Vec<Subcommand> {
+    let configured = cargo_config.commands();
+    if !configured.is_empty() {
+        return configured
+            .iter()
+            .map(|command| Subcommand {
+                title: if command.title.is_empty() {
+                    command.keyword.clone()
+                } else {
+                    command.title.clone()
+                },
+                ..command.clone()
+            })
+            .collect();
+    }
+
+    let name = cargo_config.name();
+    let mut commands = vec![Subcommand {
+        keyword: name.to_owned(),
+        title: name.to_owned(),
+        description: cargo_config.description().to_owned(),
+        args: vec![],
+    }];
+    match help(bin_path) {
+        Ok(help) => {
+            let derived = parse_help(&help)
+                .into_iter()
+                .map(|(sub, description)| Subcommand {
+                    keyword: format!("{}-{}", name, sub),
+                    title: format!("{} {}", name, sub),
+                    description,
+                    args: vec![sub],
+                });
+            commands.extend(derived);
+        }
+        Err(err) => debug!("help: {}", err),
+    }
+    commands
+}
+
+fn help(bin_path: &Path) -> Result<String> {
+    let mut cmd = Command::new(bin_path);
+    cmd.arg("--help");
+    let output = shim::exec(cmd, Some(Duration::from_millis(HELP_TIMEOUT_MS)))?;
+    Ok(output.stdout().to_owned())
+}
+
+pub fn parse_help(help: &str) -> Vec<(String, String)> {
+    let mut lines = help
+        .lines()
+        .skip_while(|line| !HELP_HEADERS.contains(&line.trim().to_lowercase().as_str()))
+        .skip(1);
+
+    let mut subcommands: Vec<(String, String)> = vec![];
+    let mut indent = None;
+    // The wrapped lines of the skipped help aren't appended to the previous subcommand
+    let mut wrapping = false;
+    while let Some(line) = lines.next().filter(|line| line.starts_with(' ')) {
+        let width = line.len() - line.trim_start().len();
+        // Deeper lines are the wrapped descriptions
+        if *indent.get_or_insert(width) != width {
+            if let (true, Some((_, description))) = (wrapping, subcommands.last_mut()) {
+                description.push(' ');
+                description.push_str(line.trim());
+            }
+            continue;
+        }
+        wrapping = false;
+        let mut words = line.split_whitespace();
+        let sub = match words.next() {
+            Some(sub) => sub.trim_end_matches(','),
+            None => continue,
+        };
+        if sub == "help" {
+            continue;
+        }
+        let description = words.collect::<Vec<&str>>().join(" ");
+        subcommands.push((sub.to_owned(), description));
+        wrapping = true;
+    }
+    subcommands
+}
+
+#[cfg(test)]
+mod tests {
+
+    use crate::subcommand::*;
+
+    const CLAP2_HELP: &str = r#"tool 0.1.0
+
+USAGE:
+    tool <SUBCOMMAND>
+
+FLAGS:
+    -h, --help       Prints help information
+
+SUBCOMMANDS:
+    decode    Decode the input
+    encode    Encode the input with a long description
+              that is wrapped
+    help      Prints this message or the help of the given subcommand(s)
+              that is wrapped too
+    verify    Verify the input
+"#;
+
+    const CLAP4_HELP: &str = r#"Usage: tool <COMMAND>
+
+Commands:
+  decode  Decode the input
+  help    Print this message or the help of the given subcommand(s)
+
+Options:
+  -h, --help  Print help
+"#;
+
+    #[test]
+    fn parse_help_clap2_ok() {
+        let actual = parse_help(CLAP2_HELP);
+        let expected = vec![
+            (String::from("decode"), String::from("Decode the input")),
+            (
+                String::from("encode"),
+                String::from("Encode the input with a long description that is wrapped"),
+            ),
+            (String::from("verify"), String::from("Verify the input")),
+        ];
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn parse_help_clap4_ok() {
+        let actual = parse_help(CLAP4_HELP);
+        let expected = vec![(String::from("decode"), String::from("Decode the input"))];
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn parse_help_none_ok() {
+        let actual = parse_help("Usage: tool [OPTIONS]\n");
+        assert!(actual.is_empty());
+    }
+
+    #[test]
+    fn resolve_notfound_bin_ok() {
+        let cargo_config = CargoConfig::dummy("test-bin");
+        let actual = resolve(&cargo_config, Path::new("cargo-launcher-notfound-bin"));
+        assert_eq!(actual.len(), 1);
+        assert_eq!(actual[0].keyword, "test-bin");
+        assert!(actual[0].args.is_empty());
+    }
+
+    #[test]
+    fn run_options_prefix_ok() {
+        let cargo_config = CargoConfig::dummy("test-bin");
+        let command = Subcommand {
+            keyword: String::from("test-bin-encode"),
+            title: String::from("test-bin encode"),
+            description: String::new(),
+            args: vec![String::from("encode")],
+        };
+        let actual = command.run_options(&cargo_config);
+        assert_eq!(
+            actual,
+            vec![
+                "--tokenize",
+                "whitespace",
+                "--keyword",
+                "test-bin-encode",
+                "--prefix-arg",
+                "encode"
+            ]
+        );
+    }
+}
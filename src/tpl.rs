@@ -1,3 +1,4 @@
+use failure::err_msg;
 use tera::{Context, Tera};
 
 use crate::error::Result;
@@ -10,6 +11,11 @@ pub fn render(tpl: &str, params: &Param) -> Result<String> {
     Ok(info_plist)
 }
 
+pub fn render_raw(tpl: &str, params: &Param) -> Result<String> {
+    let contents = Tera::one_off(tpl, &params, false).map_err(|e| err_msg(e.to_string()))?;
+    Ok(contents)
+}
+
 #[cfg(test)]
 mod tests {
 
@@ -24,4 +30,21 @@ mod tests {
         let actual = render(args, &params).unwrap();
         assert_eq!(expected, actual);
     }
+
+    #[test]
+    fn render_raw_not_escaped_ok() {
+        let args = "path: {{path}}";
+        let expected = "path: ~/.cargo/bin";
+        let mut params = Param::new();
+        params.insert("path", "~/.cargo/bin");
+        let actual = render_raw(args, &params).unwrap();
+        assert_eq!(expected, actual);
+    }
+
+    #[test]
+    fn render_raw_invalid_ng() {
+        let args = "path: {{path";
+        let actual = render_raw(args, &Param::new());
+        assert!(actual.is_err());
+    }
 }
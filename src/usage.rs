@@ -0,0 +1,141 @@
+use log::*;
+use std::time::Duration;
+
+use crate::args::RunArgs;
+use crate::history;
+use crate::item::{ActionKind, Item};
+use crate::shim;
+use crate::subcommand::parse_help;
+
+const HELP_TIMEOUT: Duration = Duration::from_secs(3);
+const RECENT_SUBTITLE: &str = "Recent query";
+
+// What the empty query lists: the run of the binary, its usage, or the recent queries
+value_enum! {
+    pub enum EmptyQuery: Run {
+        Run => "run",
+        Usage => "usage",
+        Recent => "recent",
+    }
+}
+
+// The description and the usage line of the help, followed by the subcommands to type next
+pub fn items(args: &RunArgs) -> Vec<Item> {
+    let help = help(args);
+    let name = args.keyword.clone().unwrap_or_else(|| shim::bin_name(args));
+    let title = match args.description.as_deref() {
+        Some(description) if !description.trim().is_empty() => description.trim().to_owned(),
+        _ => format!("Type the arguments of {}", name),
+    };
+    let subtitle = help
+        .as_deref()
+        .and_then(usage_line)
+        .map(|usage| format!("Usage: {}", usage))
+        .unwrap_or_else(|| String::from("Type the arguments"));
+    // The whole help is copied, the text based launchers print it
+    let mut items = vec![Item {
+        subtitle: Some(subtitle),
+        arg: help.clone(),
+        preview: help.clone(),
+        action: Some(ActionKind::Copy),
+        ..Item::text(&title)
+    }];
+
+    let subcommands = help.as_deref().map(parse_help).unwrap_or_default();
+    items.extend(subcommands.into_iter().map(|(sub, description)| {
+        let query = format!("{} ", sub);
+        Item {
+            subtitle: Some(description).filter(|description| !description.is_empty()),
+            arg: Some(query.clone()),
+            action: Some(ActionKind::Rerun),
+            autocomplete: Some(query),
+            ..Item::text(&sub)
+        }
+    }));
+    items
+}
+
+// Searched again by the action, the usage is shown while there's no query yet
+pub fn recent_items(args: &RunArgs) -> Vec<Item> {
+    let recent = history::recent(args);
+    if recent.is_empty() {
+        return items(args);
+    }
+    recent
+        .into_iter()
+        .map(|query| Item {
+            subtitle: Some(String::from(RECENT_SUBTITLE)),
+            arg: Some(query.clone()),
+            action: Some(ActionKind::Rerun),
+            autocomplete: Some(query.clone()),
+            ..Item::text(&query)
+        })
+        .collect()
+}
+
+// Some tools print the help to stderr, or exit with non-zero code
+fn help(args: &RunArgs) -> Option<String> {
+    let mut cmd = shim::base_command(args);
+    cmd.args(&args.prefix_args).arg("--help");
+    match shim::exec(cmd, Some(HELP_TIMEOUT)) {
+        Ok(output) => [output.stdout, output.stderr]
+            .iter()
+            .map(|text| text.trim())
+            .find(|text| !text.is_empty())
+            .map(String::from),
+        Err(err) => {
+            debug!("help: {}", err);
+            None
+        }
+    }
+}
+
+// clap 2 prints "USAGE:" and the usage in the next line, clap 3 or later prints "Usage: ..."
+fn usage_line(help: &str) -> Option<String> {
+    let mut lines = help.lines().map(str::trim);
+    let line = lines.find(|line| line.to_lowercase().starts_with("usage:"))?;
+    let rest = line["usage:".len()..].trim();
+    if !rest.is_empty() {
+        return Some(rest.to_owned());
+    }
+    lines.find(|line| !line.is_empty()).map(String::from)
+}
+
+#[cfg(test)]
+mod tests {
+
+    use crate::usage::*;
+
+    #[test]
+    fn usage_line_ok() {
+        let clap2 = "tool 0.1.0\n\nUSAGE:\n    tool <SUBCOMMAND>\n\nFLAGS:\n";
+        assert_eq!(usage_line(clap2).as_deref(), Some("tool <SUBCOMMAND>"));
+        let clap4 = "Encode things\n\nUsage: tool [OPTIONS] <COMMAND>\n";
+        assert_eq!(
+            usage_line(clap4).as_deref(),
+            Some("tool [OPTIONS] <COMMAND>")
+        );
+        assert_eq!(usage_line("no usage"), None);
+    }
+
+    #[test]
+    #[cfg(not(target_os = "windows"))]
+    fn items_ok() {
+        let mut args = RunArgs::dummy("sh");
+        let help = "Usage: tool <COMMAND>\n\nCommands:\n  encode  Encode the input\n";
+        args.prefix_args = vec![
+            String::from("-c"),
+            format!("printf '{}'", help),
+            String::from("sh"),
+        ];
+        args.keyword = Some(String::from("tool"));
+        args.description = Some(String::from("Encode things"));
+        let actual = items(&args);
+        assert_eq!(actual.len(), 2);
+        assert_eq!(actual[0].title, "Encode things");
+        assert_eq!(actual[0].subtitle.as_deref(), Some("Usage: tool <COMMAND>"));
+        assert_eq!(actual[1].title, "encode");
+        assert_eq!(actual[1].autocomplete.as_deref(), Some("encode "));
+        assert_eq!(actual[1].action, Some(ActionKind::Rerun));
+    }
+}
@@ -0,0 +1,179 @@
+use failure::*;
+use log::*;
+use std::path::PathBuf;
+
+use crate::block;
+use crate::cargo::CargoConfig;
+use crate::error::Result;
+use crate::fs::{backup_file, mk_dir, read_file, write_file};
+use crate::launcher::{LauncherConfig, LauncherLike};
+use crate::notify::OutputMode;
+use crate::tpl::{self, Param};
+
+const PLUGIN_TOML_BIN: &[u8] = include_bytes!("asset/walker/plugin.toml");
+const HERE_DOC_END: &str = "CARGO_LAUNCHER_EOF";
+
+pub struct Walker<'a> {
+    cargo_config: &'a CargoConfig,
+    launcher_config: &'a LauncherConfig<'a>,
+}
+
+impl<'a> Walker<'a> {
+    pub fn new(cargo_config: &'a CargoConfig, launcher_config: &'a LauncherConfig) -> Walker<'a> {
+        Walker {
+            cargo_config,
+            launcher_config,
+        }
+    }
+
+    fn application_config(&self) -> Result<PathBuf> {
+        let mut path = dirs::config_dir().ok_or_else(|| err_msg("Notfound config dir"))?;
+        path.push("walker/config.toml");
+        Ok(path)
+    }
+
+    fn plugin_toml_path(&self) -> PathBuf {
+        let mut buf = self.launcher_config.work_dir.clone();
+        buf.push(format!("{}.walker.toml", self.cargo_config.name()));
+        buf
+    }
+
+    // The term never leaves the here-document, the shim reads it from the stdin
+    fn src(&self) -> String {
+        let command = format!(
+            "{} launcher run --launcher walker {} --query-stdin -- {}",
+            shell_words::quote(&self.launcher_config.shim()),
+            shell_words::join(self.cargo_config.run_options()),
+            shell_words::quote(&self.launcher_config.bin())
+        );
+        here_doc(&command, "%TERM%")
+    }
+
+    // The notify output runs the binary on the selection, the result is the query itself
+    fn cmd(&self) -> String {
+        let command = match self.cargo_config.output() {
+            OutputMode::Notify => format!(
+                "{} launcher run --launcher walker {} --submit --query-stdin -- {}",
+                shell_words::quote(&self.launcher_config.shim()),
+                shell_words::join(self.cargo_config.run_options()),
+                shell_words::quote(&self.launcher_config.bin())
+            ),
+            OutputMode::List => String::from("wl-copy --trim-newline"),
+        };
+        here_doc(&command, "%RESULT%")
+    }
+
+    fn plugin_toml(&self) -> Result<String> {
+        let conf = self.cargo_config;
+        let mut params = Param::new();
+        params.insert("name", conf.name());
+        params.insert("prefix", &format!("/{}", conf.name()));
+        params.insert("placeholder", conf.description());
+        params.insert("src", &self.src());
+        params.insert("cmd", &self.cmd());
+
+        let tpl = String::from_utf8_lossy(PLUGIN_TOML_BIN).into_owned();
+        let contents = tpl::render_raw(&tpl, &params)?;
+
+        Ok(contents)
+    }
+}
+
+// Walker replaces the placeholder as is and runs the command by the shell,
+// nothing is expanded in the quoted here-document and the one line entry can't end it
+fn here_doc(command: &str, placeholder: &str) -> String {
+    format!(
+        "{} <<'{end}'\n{}\n{end}",
+        command,
+        placeholder,
+        end = HERE_DOC_END
+    )
+}
+
+impl<'a> LauncherLike for Walker<'a> {
+    fn before_check(&self) -> Result<()> {
+        if cfg!(not(target_os = "linux")) {
+            bail!("Walker supported only linux")
+        }
+        Ok(())
+    }
+
+    fn gen(&self) -> Result<Vec<PathBuf>> {
+        let plugin = self.plugin_toml_path();
+        write_file(&plugin, self.plugin_toml()?.as_bytes())?;
+
+        Ok(vec![plugin])
+    }
+
+    fn deploy(&self, paths: Vec<PathBuf>) -> Result<()> {
+        let config = self.application_config()?;
+        let parent = config.parent().ok_or_else(|| err_msg("Not file type"))?;
+        mk_dir(parent)?;
+        let backup = backup_file(&config)?;
+        debug!("config: {:?}", &config);
+        debug!("backup: {:?}", &backup);
+
+        let mut contents = if config.exists() {
+            String::from_utf8(read_file(&config)?)?
+        } else {
+            String::new()
+        };
+        for path in paths {
+            debug!("path: {:?}", &path);
+            let body = String::from_utf8(read_file(&path)?)?;
+            contents = block::upsert(&contents, self.cargo_config.name(), &body);
+        }
+        write_file(&config, contents.as_bytes())?;
+        Ok(())
+    }
+
+    fn completed_message(&self) -> Result<String> {
+        let msg = format!(
+            r#"
+Install completed!!
+Restart of the walker is required, then type "/{} " to run.
+
+Installed path: "#,
+            self.cargo_config.name()
+        );
+        let path = self.application_config()?;
+        Ok(format!("{}{}", msg, path.to_string_lossy()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use crate::walker::*;
+    use std::process::Command;
+    use tempdir::TempDir;
+
+    #[test]
+    fn cmd_ok() {
+        let cargo_config = CargoConfig::dummy("test-bin");
+        let launcher_config = LauncherConfig::dummy();
+        let walker = Walker::new(&cargo_config, &launcher_config);
+        assert_eq!(
+            walker.cmd(),
+            "wl-copy --trim-newline <<'CARGO_LAUNCHER_EOF'\n%RESULT%\nCARGO_LAUNCHER_EOF"
+        );
+    }
+
+    #[test]
+    #[cfg(not(target_os = "windows"))]
+    fn here_doc_injection_ok() {
+        let dir = TempDir::new("walker").unwrap();
+        let term = "x'; touch injected; $(touch injected) `touch injected` \"";
+        let script = here_doc("cat", "%TERM%").replace("%TERM%", term);
+        let output = Command::new("sh")
+            .args(["-c", &script])
+            .current_dir(dir.path())
+            .output()
+            .unwrap();
+        assert_eq!(
+            String::from_utf8_lossy(&output.stdout),
+            format!("{}\n", term)
+        );
+        assert!(!dir.path().join("injected").exists());
+    }
+}
@@ -0,0 +1,139 @@
+use failure::*;
+use std::env;
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+
+use crate::error::Result;
+
+// Same order as cargo install uses for the root dir, then the dirs of the common package managers
+fn search_dirs(
+    cargo_install_root: Option<OsString>,
+    cargo_home: Option<OsString>,
+    home: Option<PathBuf>,
+    path: Option<OsString>,
+) -> Vec<PathBuf> {
+    let mut dirs = vec![];
+    if let Some(root) = cargo_install_root {
+        dirs.push(PathBuf::from(root).join("bin"));
+    }
+    match (cargo_home, &home) {
+        (Some(cargo_home), _) => dirs.push(PathBuf::from(cargo_home).join("bin")),
+        (None, Some(home)) => dirs.push(home.join(".cargo/bin")),
+        _ => (),
+    }
+    if let Some(path) = path {
+        dirs.extend(env::split_paths(&path));
+    }
+    if let Some(home) = &home {
+        dirs.push(home.join(".local/bin"));
+        dirs.push(home.join(".nix-profile/bin"));
+    }
+    dirs.push(PathBuf::from("/usr/local/bin"));
+    dirs.push(PathBuf::from("/opt/homebrew/bin"));
+    dirs.push(PathBuf::from("/run/current-system/sw/bin"));
+    dirs
+}
+
+fn is_executable(path: &Path) -> bool {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        path.metadata()
+            .map(|m| m.is_file() && m.permissions().mode() & 0o111 != 0)
+            .unwrap_or(false)
+    }
+    #[cfg(not(unix))]
+    {
+        path.is_file()
+    }
+}
+
+fn find_in(name: &str, dirs: &[PathBuf]) -> Option<PathBuf> {
+    let file_name = format!("{}{}", name, env::consts::EXE_SUFFIX);
+    dirs.iter()
+        .map(|dir| dir.join(&file_name))
+        .find(|path| is_executable(path))
+}
+
+pub fn which(name: &str) -> Result<PathBuf> {
+    let dirs = search_dirs(
+        env::var_os("CARGO_INSTALL_ROOT"),
+        env::var_os("CARGO_HOME"),
+        dirs::home_dir(),
+        env::var_os("PATH"),
+    );
+    find_in(name, &dirs).ok_or_else(|| {
+        format_err!(
+            "Notfound {} binary. Install it with `cargo install --path .` or specify --bin-path",
+            name
+        )
+    })
+}
+
+#[cfg(test)]
+#[cfg(not(target_os = "windows"))]
+mod tests {
+
+    use crate::fs::write_file;
+    use crate::which::*;
+    use std::fs;
+    use std::os::unix::fs::PermissionsExt;
+    use tempdir::TempDir;
+
+    fn create_bin(dir: &Path, name: &str, mode: u32) -> PathBuf {
+        fs::create_dir_all(dir).unwrap();
+        let path = dir.join(name);
+        write_file(&path, b"#!/bin/sh").unwrap();
+        fs::set_permissions(&path, fs::Permissions::from_mode(mode)).unwrap();
+        path
+    }
+
+    #[test]
+    fn search_dirs_cargo_order_ok() {
+        let actual = search_dirs(
+            Some(OsString::from("/root")),
+            Some(OsString::from("/cargo")),
+            Some(PathBuf::from("/home/test")),
+            Some(OsString::from("/path1:/path2")),
+        );
+        assert_eq!(actual[0], PathBuf::from("/root/bin"));
+        assert_eq!(actual[1], PathBuf::from("/cargo/bin"));
+        assert_eq!(actual[2], PathBuf::from("/path1"));
+        assert_eq!(actual[3], PathBuf::from("/path2"));
+        assert!(actual.contains(&PathBuf::from("/opt/homebrew/bin")));
+    }
+
+    #[test]
+    fn search_dirs_default_cargo_home_ok() {
+        let actual = search_dirs(None, None, Some(PathBuf::from("/home/test")), None);
+        assert_eq!(actual[0], PathBuf::from("/home/test/.cargo/bin"));
+    }
+
+    #[test]
+    fn find_in_first_dir_ok() {
+        let tmp_dir = TempDir::new("find_in_first_dir_ok").unwrap();
+        let first = tmp_dir.path().join("first");
+        let second = tmp_dir.path().join("second");
+        let expected = create_bin(&first, "test-bin", 0o755);
+        create_bin(&second, "test-bin", 0o755);
+
+        let actual = find_in("test-bin", &[first, second]);
+        assert_eq!(actual, Some(expected));
+    }
+
+    #[test]
+    fn find_in_not_executable_ng() {
+        let tmp_dir = TempDir::new("find_in_not_executable_ng").unwrap();
+        let dir = tmp_dir.path().join("bin");
+        create_bin(&dir, "test-bin", 0o644);
+
+        let actual = find_in("test-bin", &[dir]);
+        assert!(actual.is_none());
+    }
+
+    #[test]
+    fn which_notfound_ng() {
+        let actual = which("cargo-launcher-notfound-bin");
+        assert!(actual.is_err());
+    }
+}